@@ -1,18 +1,26 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use rsp_client_executor::{io::ClientExecutorInput, ClientExecutor, EthereumVariant};
+use rsp_client_executor::{
+    io::{ClientExecutorInput, ClientExecutorOptions},
+    ClientExecutor, EthereumVariant,
+};
 
 pub fn main() {
     // Read the input.
     let input = sp1_zkvm::io::read_vec();
     let input = bincode::deserialize::<ClientExecutorInput>(&input).unwrap();
+    let options = sp1_zkvm::io::read_vec();
+    let options = bincode::deserialize::<ClientExecutorOptions>(&options).unwrap();
 
     // Execute the block.
     let executor = ClientExecutor;
-    let header = executor.execute::<EthereumVariant>(input).expect("failed to execute client");
+    let header = executor
+        .execute_with_options::<EthereumVariant>(input, &options)
+        .expect("failed to execute client");
     let block_hash = header.hash_slow();
 
-    // Commit the block hash.
+    // Commit the block hash, and whether the block was validated after execution.
     sp1_zkvm::io::commit(&block_hash);
+    sp1_zkvm::io::commit(&options.skip_post_execution_validation);
 }
@@ -0,0 +1,187 @@
+use clap::ValueEnum;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// How the cached client inputs are laid out in the cache directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CacheLayout {
+    /// One directory per chain: `input/{chain_id}/{block_number}.{ext}`.
+    Nested,
+    /// All inputs in a single directory: `input/{chain_id}-{block_number}.{ext}`.
+    Flat,
+}
+
+impl CacheLayout {
+    /// Returns the path of the cached input of a block, with the given file extension.
+    pub fn input_path(
+        &self,
+        cache_dir: &Path,
+        chain_id: u64,
+        block_number: u64,
+        extension: &str,
+    ) -> PathBuf {
+        match self {
+            CacheLayout::Nested => cache_dir
+                .join("input")
+                .join(chain_id.to_string())
+                .join(format!("{}.{}", block_number, extension)),
+            CacheLayout::Flat => {
+                cache_dir.join("input").join(format!("{}-{}.{}", chain_id, block_number, extension))
+            }
+        }
+    }
+
+    /// Removes the least recently written cached inputs of a chain, so that at most `max_blocks`
+    /// inputs with the given file extension are kept. The input of `written_block_number` is never
+    /// removed, so that it survives backfilling older blocks.
+    pub fn evict_inputs(
+        &self,
+        cache_dir: &Path,
+        chain_id: u64,
+        extension: &str,
+        max_blocks: usize,
+        written_block_number: u64,
+    ) -> io::Result<()> {
+        let block_numbers = self.cached_block_numbers(cache_dir, chain_id, extension)?;
+        if block_numbers.len() <= max_blocks {
+            return Ok(());
+        }
+
+        let mut inputs = vec![];
+        for block_number in block_numbers {
+            if block_number == written_block_number {
+                continue;
+            }
+            let input_path = self.input_path(cache_dir, chain_id, block_number, extension);
+            let modified = std::fs::metadata(&input_path)?.modified()?;
+            inputs.push((modified, input_path));
+        }
+
+        inputs.sort_unstable();
+        let evicted = inputs.len() + 1 - max_blocks.max(1);
+        for (_, input_path) in &inputs[..evicted] {
+            std::fs::remove_file(input_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the block numbers of the cached inputs of a chain with the given file extension.
+    fn cached_block_numbers(
+        &self,
+        cache_dir: &Path,
+        chain_id: u64,
+        extension: &str,
+    ) -> io::Result<Vec<u64>> {
+        let input_path = self.input_path(cache_dir, chain_id, 0, extension);
+        let Some(input_folder) = input_path.parent().filter(|folder| folder.exists()) else {
+            return Ok(vec![]);
+        };
+
+        let suffix = format!(".{}", extension);
+        let prefix = match self {
+            CacheLayout::Nested => String::new(),
+            CacheLayout::Flat => format!("{}-", chain_id),
+        };
+
+        let mut block_numbers = vec![];
+        for entry in std::fs::read_dir(input_folder)? {
+            let file_name = entry?.file_name();
+            let block_number = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(&suffix))
+                .and_then(|name| name.parse::<u64>().ok());
+            if let Some(block_number) = block_number {
+                block_numbers.push(block_number);
+            }
+        }
+
+        Ok(block_numbers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    const CHAIN_ID: u64 = 1;
+    const EXTENSION: &str = "bin";
+
+    /// Writes empty cached inputs for the given blocks, each one more recently than the previous.
+    fn write_inputs(layout: CacheLayout, cache_dir: &Path, block_numbers: &[u64]) {
+        for (i, block_number) in block_numbers.iter().enumerate() {
+            let input_path = layout.input_path(cache_dir, CHAIN_ID, *block_number, EXTENSION);
+            std::fs::create_dir_all(input_path.parent().unwrap()).unwrap();
+            let file = std::fs::File::create(&input_path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64)).unwrap();
+        }
+    }
+
+    /// Returns the sorted block numbers of the cached inputs.
+    fn cached_block_numbers(layout: CacheLayout, cache_dir: &Path) -> Vec<u64> {
+        let mut block_numbers =
+            layout.cached_block_numbers(cache_dir, CHAIN_ID, EXTENSION).unwrap();
+        block_numbers.sort_unstable();
+        block_numbers
+    }
+
+    #[test]
+    fn test_evict_inputs() {
+        for layout in [CacheLayout::Nested, CacheLayout::Flat] {
+            for (max_blocks, expected) in [(0, vec![5]), (1, vec![5]), (3, vec![3, 4, 5])] {
+                let cache_dir = tempfile::tempdir().unwrap();
+                write_inputs(layout, cache_dir.path(), &[1, 2, 3, 4, 5]);
+
+                layout.evict_inputs(cache_dir.path(), CHAIN_ID, EXTENSION, max_blocks, 5).unwrap();
+                assert_eq!(cached_block_numbers(layout, cache_dir.path()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_evict_inputs_within_limit() {
+        for layout in [CacheLayout::Nested, CacheLayout::Flat] {
+            let cache_dir = tempfile::tempdir().unwrap();
+            write_inputs(layout, cache_dir.path(), &[1, 2, 3]);
+
+            layout.evict_inputs(cache_dir.path(), CHAIN_ID, EXTENSION, 3, 3).unwrap();
+            assert_eq!(cached_block_numbers(layout, cache_dir.path()), vec![1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn test_evict_inputs_keeps_written_block() {
+        for layout in [CacheLayout::Nested, CacheLayout::Flat] {
+            // The written block is the least recently written one, as when its input was already
+            // cached before newer blocks were.
+            let cache_dir = tempfile::tempdir().unwrap();
+            write_inputs(layout, cache_dir.path(), &[1, 2, 3, 4, 5]);
+
+            layout.evict_inputs(cache_dir.path(), CHAIN_ID, EXTENSION, 2, 1).unwrap();
+            assert_eq!(cached_block_numbers(layout, cache_dir.path()), vec![1, 5]);
+        }
+    }
+
+    #[test]
+    fn test_evict_inputs_flat_prefix() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        write_inputs(CacheLayout::Flat, cache_dir.path(), &[1, 2, 3]);
+
+        // Inputs of other chains, with other extensions or unrelated files are left alone.
+        let input_dir = cache_dir.path().join("input");
+        let others = ["10-1.bin", "11-2.bin", "1-4.json", "1-latest.bin", "notes.txt"];
+        for file_name in others {
+            std::fs::File::create(input_dir.join(file_name)).unwrap();
+        }
+
+        CacheLayout::Flat.evict_inputs(cache_dir.path(), CHAIN_ID, EXTENSION, 1, 3).unwrap();
+        assert_eq!(cached_block_numbers(CacheLayout::Flat, cache_dir.path()), vec![3]);
+        for file_name in others {
+            assert!(input_dir.join(file_name).exists(), "{} was evicted", file_name);
+        }
+    }
+}
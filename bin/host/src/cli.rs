@@ -1,7 +1,20 @@
-use alloy_provider::{network::AnyNetwork, Provider as _, ReqwestProvider};
-use clap::Parser;
+use alloy_provider::{network::AnyNetwork, Provider as _, RootProvider};
+use alloy_rpc_client::ClientBuilder;
+use alloy_transport::layers::{RetryBackoffLayer, RetryBackoffService};
+use alloy_transport_http::ReqwestTransport;
+use clap::{Parser, ValueEnum};
+use sp1_sdk::{NetworkProver, ProverClient};
 use url::Url;
 
+/// The maximum number of retries for rate-limited RPC requests.
+const MAX_RATE_LIMIT_RETRIES: u32 = 10;
+
+/// The initial backoff in milliseconds for rate-limited RPC requests.
+const INITIAL_BACKOFF_MS: u64 = 1000;
+
+/// A provider that retries rate-limited requests with backoff.
+pub type RetryProvider = RootProvider<RetryBackoffService<ReqwestTransport>, AnyNetwork>;
+
 /// The arguments for configuring the chain data provider.
 #[derive(Debug, Clone, Parser)]
 pub struct ProviderArgs {
@@ -10,13 +23,36 @@ pub struct ProviderArgs {
     #[clap(long)]
     rpc_url: Option<Url>,
     /// The chain ID. If not provided, requires the rpc_url argument to be provided.
+    ///
+    /// When used together with rpc_url, this takes precedence over the chain ID reported by the
+    /// RPC node, which allows executing blocks against a local fork that reports a dev chain ID. A
+    /// warning is logged when the two differ.
     #[clap(long)]
     chain_id: Option<u64>,
+    /// The compute units per second budget of the RPC endpoint, used to throttle requests. Raise
+    /// it for endpoints with generous rate limits.
+    #[clap(long, default_value_t = 100)]
+    compute_units_per_second: u64,
 }
 
 pub struct ProviderConfig {
     pub rpc_url: Option<Url>,
     pub chain_id: u64,
+    pub compute_units_per_second: u64,
+}
+
+/// Creates a provider for the given RPC URL that retries rate-limited requests, assuming the
+/// endpoint allows `compute_units_per_second` compute units per second.
+pub fn create_provider(rpc_url: Url, compute_units_per_second: u64) -> RetryProvider {
+    let client = ClientBuilder::default()
+        .layer(RetryBackoffLayer::new(
+            MAX_RATE_LIMIT_RETRIES,
+            INITIAL_BACKOFF_MS,
+            compute_units_per_second,
+        ))
+        .http(rpc_url);
+
+    RootProvider::new(client)
 }
 
 impl ProviderArgs {
@@ -26,7 +62,24 @@ impl ProviderArgs {
         //
         // On the other hand chain ID is always needed.
         let (rpc_url, chain_id) = match (self.rpc_url, self.chain_id) {
-            (Some(rpc_url), Some(chain_id)) => (Some(rpc_url), chain_id),
+            (Some(rpc_url), Some(chain_id)) => {
+                // A mismatch with `eth_chainId` is only reported, as forked nodes (e.g. anvil)
+                // often report a dev chain ID while serving mainnet data.
+                let provider = create_provider(rpc_url.clone(), self.compute_units_per_second);
+                match provider.get_chain_id().await {
+                    Ok(rpc_chain_id) if rpc_chain_id != chain_id => tracing::warn!(
+                        "using --chain-id {} although the RPC node reports chain ID {}",
+                        chain_id,
+                        rpc_chain_id
+                    ),
+                    Ok(_) => {}
+                    Err(err) => {
+                        tracing::warn!("failed to fetch the chain ID of the RPC node: {}", err)
+                    }
+                }
+
+                (Some(rpc_url), chain_id)
+            }
             (None, Some(chain_id)) => {
                 match std::env::var(format!("RPC_{}", chain_id)) {
                     Ok(rpc_env_var) => {
@@ -41,8 +94,7 @@ impl ProviderArgs {
             }
             (Some(rpc_url), None) => {
                 // We can find out about chain ID from RPC.
-                let provider: ReqwestProvider<AnyNetwork> =
-                    ReqwestProvider::new_http(rpc_url.clone());
+                let provider = create_provider(rpc_url.clone(), self.compute_units_per_second);
                 let chain_id = provider.get_chain_id().await?;
 
                 (Some(rpc_url), chain_id)
@@ -52,6 +104,52 @@ impl ProviderArgs {
             }
         };
 
-        Ok(ProviderConfig { rpc_url, chain_id })
+        Ok(ProviderConfig {
+            rpc_url,
+            chain_id,
+            compute_units_per_second: self.compute_units_per_second,
+        })
+    }
+}
+
+/// The prover used to execute and prove the client program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProverKind {
+    /// Select the prover from the SP1_PROVER env var.
+    Env,
+    /// Prove on the local machine.
+    Local,
+    /// Prove on the Succinct prover network.
+    Network,
+}
+
+/// The arguments for configuring the prover.
+#[derive(Debug, Clone, Parser)]
+pub struct ProverArgs {
+    /// The prover to use.
+    #[clap(long, value_enum, default_value_t = ProverKind::Env)]
+    prover: ProverKind,
+    /// The private key used to request proofs from the prover network. Only used with
+    /// `--prover network`. The RPC URL of the prover network is read from the `PROVER_NETWORK_RPC`
+    /// env var by the SDK.
+    #[clap(long, env = "SP1_PRIVATE_KEY", hide_env_values = true)]
+    prover_key: Option<String>,
+}
+
+impl ProverArgs {
+    pub fn into_client(self) -> eyre::Result<ProverClient> {
+        Ok(match self.prover {
+            ProverKind::Env => ProverClient::new(),
+            ProverKind::Local => ProverClient::local(),
+            ProverKind::Network => {
+                // The network prover is built from the key directly, as setting env vars isn't
+                // sound once the runtime threads are running.
+                let Some(prover_key) = self.prover_key else {
+                    eyre::bail!("--prover-key must be used with --prover network")
+                };
+
+                ProverClient { prover: Box::new(NetworkProver::new_from_key(&prover_key)) }
+            }
+        })
     }
 }
@@ -1,13 +1,21 @@
-use csv::WriterBuilder;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
 use rsp_client_executor::{io::ClientExecutorInput, ChainVariant};
 use serde::{Deserialize, Serialize};
+use sp1_core_executor::syscalls::SyscallCode;
 use sp1_sdk::ExecutionReport;
-use std::{fs::OpenOptions, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
 
-#[derive(Serialize, Deserialize)]
-struct ExecutionReportData {
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionReportData {
     chain_id: u64,
     block_number: u64,
+    timestamp: u64,
+    base_fee_per_gas: Option<u64>,
     gas_used: u64,
     tx_count: usize,
     number_cycles: u64,
@@ -16,20 +24,26 @@ struct ExecutionReportData {
     bn_mul_cycles: u64,
     bn_pair_cycles: u64,
     kzg_point_eval_cycles: u64,
+    keccak_count: u64,
+    secp256k1_decompress_count: u64,
 }
 
-/// Given an execution report, print it out and write it to a CSV specified by report_path.
+/// Given an execution report, print it out and write it to a CSV specified by report_path. The
+/// `extra_columns` are appended to the row, e.g. to tell apart runs on different machines.
 pub fn process_execution_report(
     variant: ChainVariant,
     client_input: ClientExecutorInput,
     execution_report: ExecutionReport,
     report_path: PathBuf,
-) -> eyre::Result<()> {
+    extra_columns: &BTreeMap<String, String>,
+) -> eyre::Result<ExecutionReportData> {
     println!("\nExecution report:\n{}", execution_report);
 
     let chain_id = variant.chain_id();
     let executed_block = client_input.current_block;
     let block_number = executed_block.header.number;
+    let timestamp = executed_block.header.timestamp;
+    let base_fee_per_gas = executed_block.header.base_fee_per_gas;
     let gas_used = executed_block.header.gas_used;
     let tx_count = executed_block.body.len();
     let number_cycles = execution_report.total_instruction_count();
@@ -41,14 +55,15 @@ pub fn process_execution_report(
     let kzg_point_eval_cycles =
         *execution_report.cycle_tracker.get("precompile-kzg-point-evaluation").unwrap_or(&0);
 
-    // TODO: we can track individual syscalls in our CSV once we have sp1-core as a dependency
-    // let keccak_count = execution_report.syscall_counts.get(SyscallCode::KECCAK_PERMUTE);
-    // let secp256k1_decompress_count =
-    //     execution_report.syscall_counts.get(SyscallCode::SECP256K1_DECOMPRESS);
+    let keccak_count = execution_report.syscall_counts[SyscallCode::KECCAK_PERMUTE];
+    let secp256k1_decompress_count =
+        execution_report.syscall_counts[SyscallCode::SECP256K1_DECOMPRESS];
 
     let report_data = ExecutionReportData {
         chain_id,
         block_number,
+        timestamp,
+        base_fee_per_gas,
         gas_used,
         tx_count,
         number_cycles,
@@ -57,17 +72,156 @@ pub fn process_execution_report(
         bn_mul_cycles,
         bn_pair_cycles,
         kzg_point_eval_cycles,
+        keccak_count,
+        secp256k1_decompress_count,
     };
 
+    append_report_row(&report_path, &report_data, extra_columns)?;
+
+    Ok(report_data)
+}
+
+/// Prints a combined table of the cycles and gas of the executed blocks, and writes their reports
+/// to a JSON file, e.g. to compare cycle counts over representative blocks between two revisions.
+pub fn write_bench_summary(
+    reports: &[ExecutionReportData],
+    output_path: &Path,
+) -> eyre::Result<()> {
+    println!(
+        "\n{:>12} | {:>12} | {:>8} | {:>14} | {:>10} | {:>14}",
+        "block", "gas", "txs", "cycles", "syscalls", "cycles/gas"
+    );
+    for report_data in reports {
+        println!(
+            "{:>12} | {:>12} | {:>8} | {:>14} | {:>10} | {:>14.2}",
+            report_data.block_number,
+            report_data.gas_used,
+            report_data.tx_count,
+            report_data.number_cycles,
+            report_data.number_syscalls,
+            report_data.number_cycles as f64 / report_data.gas_used.max(1) as f64,
+        );
+    }
+
+    let output_file = std::fs::File::create(output_path)?;
+    serde_json::to_writer_pretty(output_file, reports)?;
+    println!("\nwrote benchmark results to {}", output_path.display());
+
+    Ok(())
+}
+
+/// Appends the row of [ExecutionReportData] and the `extra_columns` to the CSV report at
+/// `report_path`, writing the header first if the report is new.
+fn append_report_row(
+    report_path: &Path,
+    report_data: &ExecutionReportData,
+    extra_columns: &BTreeMap<String, String>,
+) -> eyre::Result<()> {
+    let (mut header, mut row) = csv_header_and_row(report_data)?;
+    for (key, value) in extra_columns {
+        header.push_field(key);
+        row.push_field(value);
+    }
+
     // Open the file for appending or create it if it doesn't exist
-    let file = OpenOptions::new().append(true).create(true).open(report_path)?;
+    let file = OpenOptions::new().read(true).append(true).create(true).open(report_path)?;
 
     // Check if the file is empty
     let file_is_empty = file.metadata()?.len() == 0;
 
-    let mut writer = WriterBuilder::new().has_headers(file_is_empty).from_writer(file);
-    writer.serialize(report_data)?;
+    // Refuse to append rows to a report written with a different set of columns.
+    if !file_is_empty {
+        let mut existing_header = String::new();
+        BufReader::new(&file).read_line(&mut existing_header)?;
+
+        let expected_header = csv_line(&header)?;
+        if existing_header.trim_end() != expected_header {
+            eyre::bail!(
+                "the existing report has different columns, use another --report-path: \
+                 expected={}, got={}",
+                expected_header,
+                existing_header.trim_end()
+            );
+        }
+    }
+
+    let mut writer = WriterBuilder::new().from_writer(file);
+    if file_is_empty {
+        writer.write_record(&header)?;
+    }
+    writer.write_record(&row)?;
     writer.flush()?;
 
     Ok(())
 }
+
+/// Returns the CSV header and row of [ExecutionReportData].
+fn csv_header_and_row(
+    report_data: &ExecutionReportData,
+) -> eyre::Result<(StringRecord, StringRecord)> {
+    let mut writer = WriterBuilder::new().has_headers(true).from_writer(vec![]);
+    writer.serialize(report_data)?;
+    let data = writer.into_inner()?;
+
+    let mut records =
+        ReaderBuilder::new().has_headers(false).from_reader(data.as_slice()).into_records();
+    let header = records.next().ok_or_else(|| eyre::eyre!("missing CSV header"))??;
+    let row = records.next().ok_or_else(|| eyre::eyre!("missing CSV row"))??;
+
+    Ok((header, row))
+}
+
+/// Returns a CSV record as a single line, without the line terminator.
+fn csv_line(record: &StringRecord) -> eyre::Result<String> {
+    let mut writer = WriterBuilder::new().from_writer(vec![]);
+    writer.write_record(record)?;
+    let data = String::from_utf8(writer.into_inner()?)?;
+
+    Ok(data.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report_data(block_number: u64) -> ExecutionReportData {
+        ExecutionReportData { chain_id: 1, block_number, ..Default::default() }
+    }
+
+    #[test]
+    fn test_append_report_row() {
+        let report_dir = tempfile::tempdir().unwrap();
+        let report_path = report_dir.path().join("report.csv");
+        let extra_columns = BTreeMap::from([("machine".to_string(), "a".to_string())]);
+
+        append_report_row(&report_path, &report_data(1), &extra_columns).unwrap();
+        append_report_row(&report_path, &report_data(2), &extra_columns).unwrap();
+
+        let mut reader = ReaderBuilder::new().from_path(&report_path).unwrap();
+        let header = reader.headers().unwrap().clone();
+        assert_eq!(header.len(), csv_header_and_row(&report_data(1)).unwrap().0.len() + 1);
+        assert_eq!(&header[1], "block_number");
+        assert_eq!(&header[header.len() - 1], "machine");
+
+        let rows = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((&rows[0][1], &rows[0][header.len() - 1]), ("1", "a"));
+        assert_eq!((&rows[1][1], &rows[1][header.len() - 1]), ("2", "a"));
+    }
+
+    #[test]
+    fn test_append_report_row_different_columns() {
+        let report_dir = tempfile::tempdir().unwrap();
+        let report_path = report_dir.path().join("report.csv");
+        append_report_row(&report_path, &report_data(1), &BTreeMap::new()).unwrap();
+        let report = std::fs::read_to_string(&report_path).unwrap();
+
+        // Adding a `--report-tag` changes the columns of the report.
+        let extra_columns = BTreeMap::from([("machine".to_string(), "a".to_string())]);
+        let err = append_report_row(&report_path, &report_data(2), &extra_columns).unwrap_err();
+        assert!(err.to_string().contains("different columns"), "{}", err);
+
+        // The report is left untouched.
+        assert_eq!(std::fs::read_to_string(&report_path).unwrap(), report);
+    }
+}
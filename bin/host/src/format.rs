@@ -0,0 +1,49 @@
+use clap::ValueEnum;
+use rsp_client_executor::io::ClientExecutorInput;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// The format of the client inputs written to and read from the cache.
+///
+/// The input is always passed to the zkVM with bincode, this only affects the cached artifacts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SerializationFormat {
+    /// Compact binary encoding, also used for the zkVM input.
+    Bincode,
+    /// JSON, for interchange with non-Rust tools.
+    Json,
+}
+
+impl SerializationFormat {
+    /// Returns the extension of the cache files written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SerializationFormat::Bincode => "bin",
+            SerializationFormat::Json => "json",
+        }
+    }
+
+    /// Serializes a [ClientExecutorInput] into `writer`.
+    pub fn serialize_to(
+        &self,
+        writer: impl Write,
+        client_input: &ClientExecutorInput,
+    ) -> eyre::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        match self {
+            SerializationFormat::Bincode => bincode::serialize_into(&mut writer, client_input)?,
+            SerializationFormat::Json => serde_json::to_writer(&mut writer, client_input)?,
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Deserializes a [ClientExecutorInput] from `reader`.
+    pub fn deserialize_from(&self, reader: impl Read) -> eyre::Result<ClientExecutorInput> {
+        let reader = BufReader::new(reader);
+        Ok(match self {
+            SerializationFormat::Bincode => bincode::deserialize_from(reader)?,
+            SerializationFormat::Json => serde_json::from_reader(reader)?,
+        })
+    }
+}
@@ -1,41 +1,157 @@
-use alloy_provider::ReqwestProvider;
 use clap::Parser;
-use reth_primitives::B256;
+use reth_primitives::{keccak256, revm_primitives::HashMap, Address, Header, B256, U256};
 use rsp_client_executor::{
-    io::ClientExecutorInput, ChainVariant, CHAIN_ID_ETH_MAINNET, CHAIN_ID_LINEA_MAINNET,
-    CHAIN_ID_OP_MAINNET,
+    io::{ClientExecutorInput, ClientExecutorOptions},
+    supported_chains, ChainVariant, ClientExecutor, EthereumVariant, LineaVariant, OptimismVariant,
+    CHAIN_ID_ETH_MAINNET, CHAIN_ID_LINEA_MAINNET, CHAIN_ID_OP_MAINNET,
 };
-use rsp_host_executor::HostExecutor;
-use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
-use std::path::PathBuf;
+use rsp_host_executor::{BlockSource, HostExecutor};
+use sp1_sdk::{
+    include_elf, ProverClient, SP1ProvingKey, SP1Stdin, SP1VerifyingKey, SP1_CIRCUIT_VERSION,
+};
+use std::{collections::BTreeMap, path::PathBuf};
 use tracing_subscriber::{
     filter::EnvFilter, fmt, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
 };
 
 mod execute;
-use execute::process_execution_report;
+use execute::{process_execution_report, write_bench_summary};
 
 mod cli;
-use cli::ProviderArgs;
+use cli::{create_provider, ProverArgs, ProviderArgs};
+
+mod cache;
+use cache::CacheLayout;
+
+mod format;
+use format::SerializationFormat;
 
 /// The arguments for the host executable.
 #[derive(Debug, Clone, Parser)]
 struct HostArgs {
     /// The block number of the block to execute.
-    #[clap(long)]
-    block_number: u64,
+    #[clap(
+        long,
+        required_unless_present_any = ["block_numbers", "block_hash"],
+        conflicts_with = "block_numbers"
+    )]
+    block_number: Option<u64>,
+    /// The hash of the block to execute, as an alternative to `--block-number` that fails if the
+    /// block has been reorged. Requires an RPC URL, and cached inputs are not read in that case
+    /// since they're indexed by block number.
+    #[clap(long, conflicts_with_all = ["block_number", "block_numbers"])]
+    block_hash: Option<B256>,
+    /// A comma-separated list of block numbers to execute one after the other with the same
+    /// prover client, e.g. to benchmark many cached blocks without setting up keys for each.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "proof_path",
+            "public_values",
+            "export_witness_db",
+            "dump_header_json",
+        ]
+    )]
+    block_numbers: Vec<u64>,
     #[clap(flatten)]
     provider: ProviderArgs,
+    #[clap(flatten)]
+    prover: ProverArgs,
     /// Whether to generate a proof or just execute the block.
     #[clap(long)]
     prove: bool,
-    /// Optional path to the directory containing cached client input. A new cache file will be
-    /// created from RPC data if it doesn't already exist.
+    /// The maximum number of storage keys requested in a single `eth_getProof` call, for RPC
+    /// providers that reject requests with too many keys.
+    #[clap(long)]
+    max_keys_per_proof: Option<usize>,
+    /// The minimum number of ancestor headers to include in the client input, regardless of how
+    /// far back the block uses `BLOCKHASH`.
+    #[clap(long)]
+    force_ancestor_window: Option<u64>,
+    /// The total number of retries of requests failing with a transient error allowed for a block,
+    /// on top of the retries of rate-limited requests. Failed requests are not retried if unset.
+    #[clap(long)]
+    rpc_retry_budget: Option<u32>,
+    /// A fragment of the error messages returned by the RPC node to retry within the retry budget,
+    /// e.g. "header not found" for providers that briefly fail to serve the chain tip. Other
+    /// errors of the node are never retried. Can be used multiple times.
+    #[clap(long = "rpc-retryable-error", requires = "rpc_retry_budget")]
+    rpc_retryable_errors: Vec<String>,
+    /// Optional path to a JSON file mapping addresses to the storage slots to fetch ahead of
+    /// execution, with a single request per account, e.g. the state requests of a previous run.
+    #[clap(long)]
+    access_list: Option<PathBuf>,
+    /// Optional path to the directory containing cached client input and proving keys. A new
+    /// cache file will be created from RPC data if it doesn't already exist.
     #[clap(long)]
     cache_dir: Option<PathBuf>,
+    /// The format of the client inputs in the cache directory. The input is always passed to the
+    /// zkVM with bincode, JSON is only meant for interchange with other tools.
+    #[clap(long, value_enum, default_value_t = SerializationFormat::Bincode)]
+    cache_format: SerializationFormat,
+    /// The layout of the client inputs in the cache directory.
+    #[clap(long, value_enum, default_value_t = CacheLayout::Nested)]
+    cache_layout: CacheLayout,
+    /// The maximum number of client inputs to keep in the cache directory for the chain. When a
+    /// new input is written, the least recently written inputs beyond this limit are removed.
+    #[clap(long, requires = "cache_dir")]
+    cache_max_blocks: Option<usize>,
+    /// Whether the client should explicitly verify the gas used by the executed transactions
+    /// against the block header.
+    #[clap(long)]
+    verify_gas_used: bool,
+    /// Only execute the first transactions of the block up to this count, natively on the host,
+    /// and print the intermediate state root. This is meant for debugging, the block is then
+    /// neither executed in the zkVM nor proven.
+    #[clap(long, conflicts_with = "prove")]
+    execute_up_to_tx: Option<usize>,
+    /// Skip the post-execution validation of the block inside the zkVM to save cycles. This is
+    /// unsound, as the receipts root is then not verified, and is only meant for benchmarking. The
+    /// flag is committed to the public values, so `rsp-verify` rejects the resulting proofs.
+    #[clap(long)]
+    skip_post_execution_validation: bool,
+    /// Execute the block natively on the host with this blob base fee instead of the one derived
+    /// from its excess blob gas, and print the resulting state root, to replay the block under
+    /// other blob fee conditions. The block is then neither executed in the zkVM nor proven.
+    #[clap(long, conflicts_with_all = ["prove", "execute_up_to_tx"])]
+    blob_base_fee_override: Option<u128>,
+    /// The maximum gas used by a block for it to be proven. Larger blocks are skipped before
+    /// spending any time on executing or proving them, and the next block is processed.
+    #[clap(long, requires = "prove")]
+    max_block_gas: Option<u64>,
+    /// The maximum number of cycles taken to execute a block for it to be proven. Proving is
+    /// skipped for blocks above it, and the next block is processed.
+    #[clap(long)]
+    max_cycles: Option<u64>,
+    /// Optional path to save the proof to when proving.
+    #[clap(long)]
+    proof_path: Option<PathBuf>,
+    /// Optional path to write the raw public values committed by the client program to, e.g. for
+    /// submitting proofs to an on-chain verifier.
+    #[clap(long)]
+    public_values: Option<PathBuf>,
+    /// Optional path to write the witness database built from the client input to, e.g. for
+    /// benchmarking execution against saved state without the proofs.
+    #[clap(long)]
+    export_witness_db: Option<PathBuf>,
+    /// Optional path to write the header derived by the client program to as JSON, e.g. to find
+    /// which field diverges from the RPC header on a mismatch. The client is run natively for
+    /// this.
+    #[clap(long)]
+    dump_header_json: Option<PathBuf>,
     /// The path to the CSV file containing the execution data.
     #[clap(long, default_value = "report.csv")]
     report_path: PathBuf,
+    /// Extra `key=value` columns to add to the execution report, e.g. the machine name or the
+    /// prover type. Can be used multiple times.
+    #[clap(long = "report-tag", value_parser = parse_report_tag)]
+    report_tags: Vec<(String, String)>,
+    /// Optional path to write the execution reports of all the executed blocks to as JSON, along
+    /// with a combined table of their cycles and gas printed at the end, e.g. to check for cycle
+    /// regressions over several representative blocks.
+    #[clap(long)]
+    bench_output: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -59,103 +175,381 @@ async fn main() -> eyre::Result<()> {
         CHAIN_ID_OP_MAINNET => ChainVariant::Optimism,
         CHAIN_ID_LINEA_MAINNET => ChainVariant::Linea,
         _ => {
-            eyre::bail!("unknown chain ID: {}", provider_config.chain_id);
+            let supported_chains = supported_chains()
+                .into_iter()
+                .map(|(chain_id, name)| format!("{} ({})", chain_id, name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            eyre::bail!(
+                "unknown chain ID: {}, supported chains: {}",
+                provider_config.chain_id,
+                supported_chains
+            );
         }
     };
 
-    let client_input_from_cache = try_load_input_from_cache(
-        args.cache_dir.as_ref(),
-        provider_config.chain_id,
-        args.block_number,
-    )?;
-
-    let client_input = match (client_input_from_cache, provider_config.rpc_url) {
-        (Some(client_input_from_cache), _) => client_input_from_cache,
-        (None, Some(rpc_url)) => {
-            // Cache not found but we have RPC
-            // Setup the provider.
-            let provider = ReqwestProvider::new_http(rpc_url);
-
-            // Setup the host executor.
-            let host_executor = HostExecutor::new(provider);
-
-            // Execute the host.
-            let client_input = host_executor
-                .execute(args.block_number, variant)
-                .await
-                .expect("failed to execute host");
+    // Load the access list to prefetch, if any.
+    let access_list = args
+        .access_list
+        .as_ref()
+        .map(|access_list_path| {
+            let access_list_file = std::fs::File::open(access_list_path)?;
+            eyre::Ok(serde_json::from_reader::<_, HashMap<Address, Vec<U256>>>(access_list_file)?)
+        })
+        .transpose()?;
+
+    // Setup the host executor, if there is a provider. It's shared by all the blocks to execute.
+    let host_executor = provider_config.rpc_url.map(|rpc_url| {
+        let provider = create_provider(rpc_url, provider_config.compute_units_per_second);
+        let mut host_executor = HostExecutor::new(provider);
+        if let Some(max_keys_per_proof) = args.max_keys_per_proof {
+            host_executor = host_executor.with_max_keys_per_proof(max_keys_per_proof);
+        }
+        if let Some(force_ancestor_window) = args.force_ancestor_window {
+            host_executor = host_executor.with_force_ancestor_window(force_ancestor_window);
+        }
+        if let Some(rpc_retry_budget) = args.rpc_retry_budget {
+            host_executor = host_executor.with_retry_budget(rpc_retry_budget);
+        }
+        if !args.rpc_retryable_errors.is_empty() {
+            host_executor = host_executor.with_retryable_errors(args.rpc_retryable_errors.clone());
+        }
+        if let Some(access_list) = access_list {
+            host_executor = host_executor.with_access_list(access_list);
+        }
+        host_executor
+    });
 
-            if let Some(cache_dir) = args.cache_dir {
-                let input_folder = cache_dir.join(format!("input/{}", provider_config.chain_id));
-                if !input_folder.exists() {
-                    std::fs::create_dir_all(&input_folder)?;
+    let block_numbers = match (args.block_number, args.block_hash) {
+        (Some(block_number), _) => vec![block_number],
+        (None, Some(block_hash)) => {
+            let Some(host_executor) = &host_executor else {
+                eyre::bail!("--block-hash requires an RPC URL")
+            };
+            vec![host_executor.block_number_by_hash(block_hash).await?]
+        }
+        (None, None) => args.block_numbers,
+    };
+
+    let client = args.prover.into_client()?;
+
+    let elf = match variant {
+        ChainVariant::Ethereum => include_elf!("rsp-client-eth"),
+        ChainVariant::Optimism => include_elf!("rsp-client-op"),
+        ChainVariant::Linea => include_elf!("rsp-client-linea"),
+    };
+    if elf.is_empty() {
+        eyre::bail!("empty client ELF: variant={:?}", variant);
+    }
+
+    let report_columns = args.report_tags.iter().cloned().collect::<BTreeMap<_, _>>();
+
+    // The proving and verification keys are only set up once, when proving the first block.
+    let mut keys = None;
+
+    // The execution reports of the blocks, for the benchmark summary.
+    let mut bench_reports = vec![];
+
+    for block_number in block_numbers {
+        let client_input_from_cache = match args.block_hash {
+            Some(_) => None,
+            None => try_load_input_from_cache(
+                args.cache_dir.as_ref(),
+                args.cache_layout,
+                args.cache_format,
+                provider_config.chain_id,
+                block_number,
+            )?,
+        };
+
+        // Skip blocks that are too large to prove, if requested, before doing any work on them.
+        if let Some(max_block_gas) = args.max_block_gas {
+            let gas_used = match (&client_input_from_cache, &host_executor) {
+                (Some(client_input_from_cache), _) => {
+                    client_input_from_cache.current_block.header.gas_used
+                }
+                (None, Some(host_executor)) => {
+                    host_executor.block_source.header(block_number).await?.gas_used
+                }
+                (None, None) => eyre::bail!("cache not found and RPC URL not provided"),
+            };
+            if gas_used > max_block_gas {
+                println!(
+                    "skipping block, too large to prove: block_number={}, gas_used={}, \
+                     max_block_gas={}",
+                    block_number, gas_used, max_block_gas
+                );
+                continue;
+            }
+        }
+
+        let client_input = match (client_input_from_cache, &host_executor) {
+            (Some(client_input_from_cache), _) => client_input_from_cache,
+            (None, Some(host_executor)) => {
+                // Cache not found but we have RPC, execute the host.
+                let client_input = match args.block_hash {
+                    Some(block_hash) => host_executor.execute_by_hash(block_hash, variant).await,
+                    None => host_executor.execute(block_number, variant).await,
                 }
+                .expect("failed to execute host");
+
+                if let Some(cache_dir) = &args.cache_dir {
+                    let input_path = args.cache_layout.input_path(
+                        cache_dir,
+                        provider_config.chain_id,
+                        block_number,
+                        args.cache_format.extension(),
+                    );
+                    if let Some(input_folder) = input_path.parent() {
+                        std::fs::create_dir_all(input_folder)?;
+                    }
+
+                    let cache_file = std::fs::File::create(input_path)?;
 
-                let input_path = input_folder.join(format!("{}.bin", args.block_number));
-                let mut cache_file = std::fs::File::create(input_path)?;
+                    args.cache_format.serialize_to(cache_file, &client_input)?;
 
-                bincode::serialize_into(&mut cache_file, &client_input)?;
+                    // Keep the size of the cache bounded, if requested.
+                    if let Some(cache_max_blocks) = args.cache_max_blocks {
+                        args.cache_layout.evict_inputs(
+                            cache_dir,
+                            provider_config.chain_id,
+                            args.cache_format.extension(),
+                            cache_max_blocks,
+                            block_number,
+                        )?;
+                    }
+                }
+
+                client_input
+            }
+            (None, None) => {
+                eyre::bail!("cache not found and RPC URL not provided")
             }
+        };
 
-            client_input
+        // Write the witness database, if requested.
+        if let Some(witness_db_path) = &args.export_witness_db {
+            let mut witness_db_file = std::fs::File::create(witness_db_path)?;
+            bincode::serialize_into(&mut witness_db_file, &client_input.witness_db()?)?;
         }
-        (None, None) => {
-            eyre::bail!("cache not found and RPC URL not provided")
+
+        // Execute part of the block natively, if requested. The result doesn't correspond to any
+        // block, so there is nothing to execute in the zkVM.
+        if let Some(tx_count) = args.execute_up_to_tx {
+            let state_root = execute_up_to_tx(variant, client_input, tx_count)?;
+            println!(
+                "partial execution: block_number={block_number}, tx_count={tx_count}, \
+                 state_root={state_root}"
+            );
+            continue;
         }
-    };
 
-    // Generate the proof.
-    let client = ProverClient::new();
+        // Replay the block under other blob fee conditions natively, if requested. The result
+        // generally doesn't correspond to the actual block, so there is nothing to execute in the
+        // zkVM.
+        if let Some(blob_base_fee) = args.blob_base_fee_override {
+            let state_root = execute_with_blob_base_fee(variant, client_input, blob_base_fee)?;
+            println!(
+                "what-if execution: block_number={block_number}, blob_base_fee={blob_base_fee}, \
+                 state_root={state_root}"
+            );
+            continue;
+        }
 
-    // Setup the proving key and verification key.
-    let (pk, vk) = client.setup(match variant {
-        ChainVariant::Ethereum => include_elf!("rsp-client-eth"),
-        ChainVariant::Optimism => include_elf!("rsp-client-op"),
-        ChainVariant::Linea => include_elf!("rsp-client-linea"),
-    });
+        // Write the derived header, if requested.
+        if let Some(header_path) = &args.dump_header_json {
+            let header = derive_header(variant, client_input.clone())?;
+            let header_file = std::fs::File::create(header_path)?;
+            serde_json::to_writer_pretty(header_file, &header)?;
+        }
+
+        // Execute the block inside the zkVM.
+        let mut stdin = SP1Stdin::new();
+        let buffer = bincode::serialize(&client_input).unwrap();
+        stdin.write_vec(buffer);
+        let client_options = ClientExecutorOptions {
+            verify_gas_used: args.verify_gas_used,
+            skip_post_execution_validation: args.skip_post_execution_validation,
+        };
+        stdin.write_vec(bincode::serialize(&client_options).unwrap());
+
+        // Only execute the program.
+        let (mut public_values, execution_report) =
+            client.execute(elf, stdin.clone()).run().unwrap();
+
+        // Write the raw public values, if requested.
+        if let Some(public_values_path) = &args.public_values {
+            std::fs::write(public_values_path, public_values.to_vec())?;
+        }
+
+        // Read the block hash.
+        let block_hash = public_values.read::<B256>();
+        println!("success: block_number={block_number}, block_hash={block_hash}");
 
-    // Execute the block inside the zkVM.
-    let mut stdin = SP1Stdin::new();
-    let buffer = bincode::serialize(&client_input).unwrap();
-    stdin.write_vec(buffer);
+        let cycles = execution_report.total_instruction_count();
 
-    // Only execute the program.
-    let (mut public_values, execution_report) =
-        client.execute(&pk.elf, stdin.clone()).run().unwrap();
+        // Process the execute report, print it out, and save data to a CSV specified by
+        // report_path.
+        let report_data = process_execution_report(
+            variant,
+            client_input,
+            execution_report,
+            args.report_path.clone(),
+            &report_columns,
+        )?;
+        bench_reports.push(report_data);
 
-    // Read the block hash.
-    let block_hash = public_values.read::<B256>();
-    println!("success: block_hash={block_hash}");
+        // Skip proving blocks that would take too long to prove, if requested.
+        if let Some(max_cycles) = args.max_cycles {
+            if args.prove && cycles > max_cycles {
+                println!(
+                    "skipping proof, too many cycles: block_number={}, cycles={}, max_cycles={}",
+                    block_number, cycles, max_cycles
+                );
+                continue;
+            }
+        }
+
+        if args.prove {
+            // Setup the proving key and verification key. This is only needed for proving, so
+            // it's skipped entirely when only executing.
+            if keys.is_none() {
+                keys = Some(setup_keys(&client, elf, args.cache_dir.as_ref())?);
+            }
+            let (pk, vk) = keys.as_ref().unwrap();
 
-    // Process the execute report, print it out, and save data to a CSV specified by
-    // report_path.
-    process_execution_report(variant, client_input, execution_report, args.report_path)?;
+            // Actually generate the proof. It is strongly recommended you use the network prover
+            // given the size of these programs.
+            println!("Starting proof generation.");
+            let proof = client.prove(pk, stdin).compressed().run().expect("Proving should work.");
+            println!("Proof generation finished.");
 
-    if args.prove {
-        // Actually generate the proof. It is strongly recommended you use the network prover
-        // given the size of these programs.
-        println!("Starting proof generation.");
-        let proof = client.prove(&pk, stdin).compressed().run().expect("Proving should work.");
-        println!("Proof generation finished.");
+            client.verify(&proof, vk).expect("proof verification should succeed");
 
-        client.verify(&proof, &vk).expect("proof verification should succeed");
+            // Check that the proof commits to the executed block, and not to some other input.
+            let proven_block_hash = proof.public_values.clone().read::<B256>();
+            if proven_block_hash != block_hash {
+                eyre::bail!(
+                    "mismatched block hash in the proof public values: expected={}, got={}",
+                    block_hash,
+                    proven_block_hash
+                );
+            }
+
+            // Save the proof, if requested, so it can be verified independently with
+            // `rsp-verify`.
+            if let Some(proof_path) = &args.proof_path {
+                proof
+                    .save(proof_path)
+                    .map_err(|err| eyre::eyre!("failed to save proof: {}", err))?;
+                println!("Proof saved to {}.", proof_path.display());
+            }
+        }
+    }
+
+    // Write the benchmark summary, if requested.
+    if let Some(bench_output) = &args.bench_output {
+        write_bench_summary(&bench_reports, bench_output)?;
     }
 
     Ok(())
 }
 
+/// Executes the client program natively to derive the header it commits the hash of.
+fn derive_header(variant: ChainVariant, client_input: ClientExecutorInput) -> eyre::Result<Header> {
+    let executor = ClientExecutor;
+    match variant {
+        ChainVariant::Ethereum => executor.execute::<EthereumVariant>(client_input),
+        ChainVariant::Optimism => executor.execute::<OptimismVariant>(client_input),
+        ChainVariant::Linea => executor.execute::<LineaVariant>(client_input),
+    }
+}
+
+/// Executes the first `tx_count` transactions of the block natively and returns the intermediate
+/// state root.
+fn execute_up_to_tx(
+    variant: ChainVariant,
+    client_input: ClientExecutorInput,
+    tx_count: usize,
+) -> eyre::Result<B256> {
+    let executor = ClientExecutor;
+    match variant {
+        ChainVariant::Ethereum => {
+            executor.execute_up_to_tx::<EthereumVariant>(client_input, tx_count)
+        }
+        ChainVariant::Optimism => {
+            executor.execute_up_to_tx::<OptimismVariant>(client_input, tx_count)
+        }
+        ChainVariant::Linea => executor.execute_up_to_tx::<LineaVariant>(client_input, tx_count),
+    }
+}
+
+/// Executes the block natively with the given blob base fee and returns the resulting state root.
+fn execute_with_blob_base_fee(
+    variant: ChainVariant,
+    client_input: ClientExecutorInput,
+    blob_base_fee: u128,
+) -> eyre::Result<B256> {
+    let executor = ClientExecutor;
+    match variant {
+        ChainVariant::Ethereum => {
+            executor.execute_with_blob_base_fee::<EthereumVariant>(client_input, blob_base_fee)
+        }
+        ChainVariant::Optimism => {
+            executor.execute_with_blob_base_fee::<OptimismVariant>(client_input, blob_base_fee)
+        }
+        ChainVariant::Linea => {
+            executor.execute_with_blob_base_fee::<LineaVariant>(client_input, blob_base_fee)
+        }
+    }
+}
+
+/// Sets up the proving and verification keys for the given ELF. If a cache directory is provided,
+/// the keys are loaded from there when available, and written there after the first setup. Keys
+/// are cached per SP1 version, as the same ELF has different keys across versions.
+fn setup_keys(
+    client: &ProverClient,
+    elf: &[u8],
+    cache_dir: Option<&PathBuf>,
+) -> eyre::Result<(SP1ProvingKey, SP1VerifyingKey)> {
+    let Some(cache_dir) = cache_dir else {
+        return Ok(client.setup(elf));
+    };
+
+    let keys_folder = cache_dir.join("keys").join(SP1_CIRCUIT_VERSION);
+    let keys_path = keys_folder.join(format!("{}.bin", keccak256(elf)));
+
+    if keys_path.exists() {
+        let mut keys_file = std::fs::File::open(keys_path)?;
+        return Ok(bincode::deserialize_from(&mut keys_file)?);
+    }
+
+    let keys = client.setup(elf);
+
+    if !keys_folder.exists() {
+        std::fs::create_dir_all(&keys_folder)?;
+    }
+    let mut keys_file = std::fs::File::create(keys_path)?;
+    bincode::serialize_into(&mut keys_file, &keys)?;
+
+    Ok(keys)
+}
+
 fn try_load_input_from_cache(
     cache_dir: Option<&PathBuf>,
+    cache_layout: CacheLayout,
+    cache_format: SerializationFormat,
     chain_id: u64,
     block_number: u64,
 ) -> eyre::Result<Option<ClientExecutorInput>> {
     Ok(if let Some(cache_dir) = cache_dir {
-        let cache_path = cache_dir.join(format!("input/{}/{}.bin", chain_id, block_number));
+        let cache_path =
+            cache_layout.input_path(cache_dir, chain_id, block_number, cache_format.extension());
 
         if cache_path.exists() {
             // TODO: prune the cache if invalid instead
-            let mut cache_file = std::fs::File::open(cache_path)?;
-            let client_input: ClientExecutorInput = bincode::deserialize_from(&mut cache_file)?;
+            let cache_file = std::fs::File::open(cache_path)?;
+            let client_input = cache_format.deserialize_from(cache_file)?;
 
             Some(client_input)
         } else {
@@ -165,3 +559,12 @@ fn try_load_input_from_cache(
         None
     })
 }
+
+/// Parses a `key=value` report tag.
+fn parse_report_tag(tag: &str) -> Result<(String, String), String> {
+    let (key, value) = tag
+        .split_once('=')
+        .ok_or_else(|| format!("invalid report tag, expected key=value: {tag}"))?;
+
+    Ok((key.to_string(), value.to_string()))
+}
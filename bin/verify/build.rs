@@ -0,0 +1,7 @@
+use sp1_helper::build_program;
+
+fn main() {
+    build_program("../client-eth");
+    build_program("../client-op");
+    build_program("../client-linea");
+}
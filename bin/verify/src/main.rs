@@ -0,0 +1,49 @@
+use clap::Parser;
+use reth_primitives::B256;
+use rsp_client_executor::{CHAIN_ID_ETH_MAINNET, CHAIN_ID_LINEA_MAINNET, CHAIN_ID_OP_MAINNET};
+use sp1_sdk::{include_elf, ProverClient, SP1ProofWithPublicValues};
+use std::path::PathBuf;
+
+/// The arguments for the verifier executable.
+#[derive(Debug, Clone, Parser)]
+struct VerifyArgs {
+    /// The path to the proof saved by the host CLI.
+    #[clap(long)]
+    proof: PathBuf,
+    /// The chain ID of the proven block, used to select the client program.
+    #[clap(long)]
+    chain_id: u64,
+}
+
+fn main() -> eyre::Result<()> {
+    // Parse the command line arguments.
+    let args = VerifyArgs::parse();
+
+    let elf = match args.chain_id {
+        CHAIN_ID_ETH_MAINNET => include_elf!("rsp-client-eth"),
+        CHAIN_ID_OP_MAINNET => include_elf!("rsp-client-op"),
+        CHAIN_ID_LINEA_MAINNET => include_elf!("rsp-client-linea"),
+        _ => {
+            eyre::bail!("unknown chain ID: {}", args.chain_id);
+        }
+    };
+
+    // Load the proof.
+    let mut proof = SP1ProofWithPublicValues::load(&args.proof)
+        .map_err(|err| eyre::eyre!("failed to load proof: {}", err))?;
+
+    // Setup the verification key and verify the proof.
+    let client = ProverClient::new();
+    let (_, vk) = client.setup(elf);
+    client.verify(&proof, &vk).map_err(|err| eyre::eyre!("invalid proof: {}", err))?;
+
+    // Read the block hash, and reject proofs of blocks that were not validated after execution.
+    let block_hash = proof.public_values.read::<B256>();
+    let skipped_post_execution_validation = proof.public_values.read::<bool>();
+    if skipped_post_execution_validation {
+        eyre::bail!("the block was not validated after execution: block_hash={}", block_hash);
+    }
+    println!("success: block_hash={block_hash}");
+
+    Ok(())
+}
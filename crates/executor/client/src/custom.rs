@@ -11,7 +11,7 @@ use reth_evm::{ConfigureEvm, ConfigureEvmEnv};
 use reth_evm_ethereum::EthEvmConfig;
 use reth_evm_optimism::OptimismEvmConfig;
 use reth_primitives::{
-    revm_primitives::{CfgEnvWithHandlerCfg, TxEnv},
+    revm_primitives::{BlockEnv, CfgEnvWithHandlerCfg, TxEnv},
     Address, Bytes, Header, TransactionSigned, U256,
 };
 use reth_revm::{
@@ -79,7 +79,12 @@ pub(crate) const ANNOTATED_BN_PAIR: PrecompileWithAddress =
 /// Custom EVM configuration
 #[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
-pub struct CustomEvmConfig(pub ChainVariant);
+pub struct CustomEvmConfig {
+    /// The chain variant the EVM is configured for.
+    pub variant: ChainVariant,
+    /// The blob base fee to use instead of the one derived from the header's excess blob gas.
+    blob_base_fee_override: Option<u128>,
+}
 
 impl CustomEvmConfig {
     /// Sets the precompiles to the EVM handler
@@ -111,7 +116,14 @@ impl CustomEvmConfig {
     }
 
     pub fn from_variant(variant: ChainVariant) -> Self {
-        Self(variant)
+        Self { variant, blob_base_fee_override: None }
+    }
+
+    /// Overrides the blob base fee of the block environment, e.g. to replay a block under other
+    /// blob fee conditions. Blocks without blob gas fields are not affected.
+    pub fn with_blob_base_fee_override(mut self, blob_base_fee: u128) -> Self {
+        self.blob_base_fee_override = Some(blob_base_fee);
+        self
     }
 }
 
@@ -119,7 +131,7 @@ impl ConfigureEvm for CustomEvmConfig {
     type DefaultExternalContext<'a> = ();
 
     fn evm<DB: Database>(&self, db: DB) -> Evm<'_, Self::DefaultExternalContext<'_>, DB> {
-        match self.0 {
+        match self.variant {
             ChainVariant::Ethereum => {
                 EvmBuilder::default()
                     .with_db(db)
@@ -150,7 +162,7 @@ impl ConfigureEvm for CustomEvmConfig {
 
 impl ConfigureEvmEnv for CustomEvmConfig {
     fn fill_tx_env(&self, tx_env: &mut TxEnv, transaction: &TransactionSigned, sender: Address) {
-        match self.0 {
+        match self.variant {
             ChainVariant::Ethereum => {
                 EthEvmConfig::default().fill_tx_env(tx_env, transaction, sender)
             }
@@ -168,7 +180,7 @@ impl ConfigureEvmEnv for CustomEvmConfig {
         header: &Header,
         total_difficulty: U256,
     ) {
-        match self.0 {
+        match self.variant {
             ChainVariant::Ethereum => {
                 EthEvmConfig::default().fill_cfg_env(cfg_env, chain_spec, header, total_difficulty)
             }
@@ -184,6 +196,26 @@ impl ConfigureEvmEnv for CustomEvmConfig {
         }
     }
 
+    fn fill_block_env(&self, block_env: &mut BlockEnv, header: &Header, after_merge: bool) {
+        match self.variant {
+            ChainVariant::Ethereum => {
+                EthEvmConfig::default().fill_block_env(block_env, header, after_merge)
+            }
+            ChainVariant::Optimism => {
+                OptimismEvmConfig::default().fill_block_env(block_env, header, after_merge)
+            }
+            ChainVariant::Linea => {
+                EthEvmConfig::default().fill_block_env(block_env, header, after_merge)
+            }
+        }
+
+        if let Some(blob_base_fee) = self.blob_base_fee_override {
+            if let Some(blob_excess_gas_and_price) = &mut block_env.blob_excess_gas_and_price {
+                blob_excess_gas_and_price.blob_gasprice = blob_base_fee;
+            }
+        }
+    }
+
     fn fill_tx_env_system_contract_call(
         &self,
         env: &mut Env,
@@ -191,7 +223,7 @@ impl ConfigureEvmEnv for CustomEvmConfig {
         contract: Address,
         data: Bytes,
     ) {
-        match self.0 {
+        match self.variant {
             ChainVariant::Ethereum => EthEvmConfig::default()
                 .fill_tx_env_system_contract_call(env, caller, contract, data),
             ChainVariant::Optimism => OptimismEvmConfig::default()
@@ -207,7 +239,7 @@ mod tests {
     use super::*;
     use reth_chainspec::{Chain, ChainSpecBuilder, EthereumHardfork};
     use reth_primitives::{
-        revm_primitives::{BlockEnv, CfgEnv, SpecId},
+        revm_primitives::{CfgEnv, SpecId},
         ForkCondition, Genesis,
     };
 
@@ -233,4 +265,61 @@ mod tests {
 
         assert_eq!(cfg_env.chain_id, chain_spec.chain().id());
     }
+
+    #[test]
+    fn test_blob_base_fee_override() {
+        let header = Header { excess_blob_gas: Some(0), ..Default::default() };
+
+        let mut block_env = BlockEnv::default();
+        CustomEvmConfig::from_variant(ChainVariant::Ethereum).fill_block_env(
+            &mut block_env,
+            &header,
+            true,
+        );
+        assert_eq!(block_env.get_blob_gasprice(), Some(1));
+
+        let mut block_env = BlockEnv::default();
+        CustomEvmConfig::from_variant(ChainVariant::Ethereum)
+            .with_blob_base_fee_override(7)
+            .fill_block_env(&mut block_env, &header, true);
+        assert_eq!(block_env.get_blob_gasprice(), Some(7));
+
+        let mut block_env = BlockEnv::default();
+        CustomEvmConfig::from_variant(ChainVariant::Optimism)
+            .with_blob_base_fee_override(7)
+            .fill_block_env(&mut block_env, &header, true);
+        assert_eq!(block_env.get_blob_gasprice(), Some(7));
+
+        // Pre-Cancun blocks have no blob base fee to override.
+        let mut block_env = BlockEnv::default();
+        CustomEvmConfig::from_variant(ChainVariant::Ethereum)
+            .with_blob_base_fee_override(7)
+            .fill_block_env(&mut block_env, &Header::default(), true);
+        assert_eq!(block_env.get_blob_gasprice(), None);
+    }
+
+    #[test]
+    fn test_spec_id_from_chain_spec() {
+        let header = Header { number: 5600000, timestamp: 1717200000, ..Default::default() };
+
+        let mut cfg_env = CfgEnvWithHandlerCfg::new_with_spec_id(CfgEnv::default(), SpecId::LATEST);
+        CustomEvmConfig::from_variant(ChainVariant::Linea).fill_cfg_env(
+            &mut cfg_env,
+            &rsp_primitives::chain_spec::linea_mainnet(),
+            &header,
+            U256::ZERO,
+        );
+        assert_eq!(cfg_env.chain_id, crate::CHAIN_ID_LINEA_MAINNET);
+        assert_eq!(cfg_env.handler_cfg.spec_id, SpecId::MERGE);
+
+        let mut cfg_env = CfgEnvWithHandlerCfg::new_with_spec_id(CfgEnv::default(), SpecId::LATEST);
+        CustomEvmConfig::from_variant(ChainVariant::Ethereum).fill_cfg_env(
+            &mut cfg_env,
+            &rsp_primitives::chain_spec::mainnet(),
+            &header,
+            U256::ZERO,
+        );
+        assert_eq!(cfg_env.chain_id, crate::CHAIN_ID_ETH_MAINNET);
+        assert_eq!(cfg_env.handler_cfg.spec_id, SpecId::CANCUN);
+    }
 }
@@ -1,8 +1,14 @@
-use std::{collections::HashMap, iter::once};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    iter::once,
+};
 
 use eyre::Result;
 use itertools::Itertools;
-use reth_primitives::{revm_primitives::AccountInfo, Address, Block, Header, B256, U256};
+use reth_primitives::{
+    revm_primitives::AccountInfo, AccessList, AccessListItem, Address, Block, Bytes, Header, B256,
+    U256,
+};
 use reth_trie::TrieAccount;
 use revm_primitives::{keccak256, Bytecode};
 use rsp_mpt::EthereumState;
@@ -29,6 +35,37 @@ pub struct ClientExecutorInput {
     pub bytecodes: Vec<Bytecode>,
 }
 
+/// Options of the client that are not part of the witness, passed to the client program separately
+/// from the [`ClientExecutorInput`].
+///
+/// These are chosen by the prover, so any option that weakens the validation of the block must be
+/// committed to the public values for verifiers to reject such proofs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientExecutorOptions {
+    /// Whether to explicitly check the gas used by the executed transactions against the gas used
+    /// in the block header.
+    pub verify_gas_used: bool,
+    /// Whether to skip the post-execution validation of the block (receipts root, logs bloom,
+    /// gas used and requests) to save cycles.
+    ///
+    /// This is unsound: the receipts root of the committed header is then taken as is from the
+    /// input. The client programs commit this flag after the block hash, and proofs with it set
+    /// must not be trusted. It is only meant for benchmarking cycle budgets.
+    pub skip_post_execution_validation: bool,
+}
+
+/// The data required to execute a block statelessly, in the same shape as the witness returned by
+/// the `debug_executionWitness` RPC method.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecutionWitness {
+    /// The RLP-encoded trie nodes of the parent state.
+    pub state: Vec<Bytes>,
+    /// The deduplicated account bytecodes.
+    pub codes: Vec<Bytes>,
+    /// The RLP-encoded ancestor headers, starting from the parent block.
+    pub headers: Vec<Bytes>,
+}
+
 impl ClientExecutorInput {
     /// Gets the immediate parent block's header.
     #[inline(always)]
@@ -40,6 +77,98 @@ impl ClientExecutorInput {
     pub fn witness_db(&self) -> Result<WitnessDb> {
         <Self as WitnessInput>::witness_db(self)
     }
+
+    /// Returns the EIP-2930 style access list of all the accounts and storage slots touched while
+    /// executing the block, sorted by address.
+    pub fn access_list(&self) -> AccessList {
+        AccessList(
+            self.state_requests
+                .iter()
+                .sorted_by_key(|(address, _)| **address)
+                .map(|(&address, slots)| AccessListItem {
+                    address,
+                    storage_keys: slots.iter().map(|slot| B256::from(*slot)).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Exports the witness collected by the host as an [`ExecutionWitness`], so it can be replayed
+    /// or shared with other tools.
+    pub fn to_execution_witness(&self) -> ExecutionWitness {
+        let codes = self
+            .bytecodes
+            .iter()
+            .map(|code| (code.hash_slow(), code.original_bytes()))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect();
+        let headers =
+            self.ancestor_headers.iter().map(|header| alloy_rlp::encode(header).into()).collect();
+
+        ExecutionWitness { state: self.parent_state.trie_nodes(), codes, headers }
+    }
+
+    /// Checks that the input contains everything needed to build a [`WitnessDb`], reporting the
+    /// exact missing entry instead of failing somewhere in the middle of execution.
+    ///
+    /// This verifies that every requested account and storage slot can be read from the parent
+    /// state without reaching an unresolved trie node, that every requested storage slot has a
+    /// corresponding storage trie, and that the bytecode of every existing account is provided.
+    pub fn validate_completeness(&self) -> Result<()> {
+        if self.ancestor_headers.is_empty() {
+            eyre::bail!("missing parent header");
+        }
+
+        let bytecode_hashes =
+            self.bytecodes.iter().map(|code| code.hash_slow()).collect::<HashSet<_>>();
+
+        for (address, slots) in self.state_requests.iter() {
+            let hashed_address = keccak256(address);
+            let hashed_address = hashed_address.as_slice();
+
+            let account_in_trie = self
+                .parent_state
+                .state_trie
+                .get_rlp::<TrieAccount>(hashed_address)
+                .map_err(|err| eyre::eyre!("failed to read account {}: {}", address, err))?;
+
+            if let Some(account_in_trie) = account_in_trie {
+                if !bytecode_hashes.contains(&account_in_trie.code_hash) {
+                    eyre::bail!(
+                        "missing bytecode for account {}: code_hash={}",
+                        address,
+                        account_in_trie.code_hash
+                    );
+                }
+            }
+
+            if slots.is_empty() {
+                continue;
+            }
+
+            let storage_trie = self
+                .parent_state
+                .storage_tries
+                .get(hashed_address)
+                .ok_or_else(|| eyre::eyre!("missing storage trie for account {}", address))?;
+
+            for slot in slots {
+                storage_trie
+                    .get_rlp::<U256>(keccak256(slot.to_be_bytes::<32>()).as_slice())
+                    .map_err(|err| {
+                        eyre::eyre!(
+                            "failed to read storage slot {} of account {}: {}",
+                            slot,
+                            address,
+                            err
+                        )
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl WitnessInput for ClientExecutorInput {
@@ -171,3 +300,135 @@ pub trait WitnessInput {
         Ok(WitnessDb { accounts, storage, block_hashes })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rlp::Decodable;
+
+    /// Returns an input requesting a storage slot of a contract, with everything needed to read it.
+    fn complete_input() -> ClientExecutorInput {
+        let address = Address::repeat_byte(1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+
+        let mut input = ClientExecutorInput {
+            current_block: Block::default(),
+            ancestor_headers: vec![Header::default()],
+            parent_state: EthereumState {
+                state_trie: Default::default(),
+                storage_tries: Default::default(),
+            },
+            state_requests: HashMap::from([
+                (address, vec![U256::from(1), U256::from(2)]),
+                (Address::repeat_byte(2), vec![]),
+            ]),
+            bytecodes: vec![code.clone()],
+        };
+
+        let account = TrieAccount { code_hash: code.hash_slow(), ..Default::default() };
+        input.parent_state.state_trie.insert_rlp(keccak256(address).as_slice(), account).unwrap();
+        let storage_trie = input.parent_state.storage_tries.entry(keccak256(address)).or_default();
+        let slot = keccak256(U256::from(1).to_be_bytes::<32>());
+        storage_trie.insert_rlp(slot.as_slice(), U256::from(3)).unwrap();
+
+        input
+    }
+
+    /// Returns a trie only known by the hash of its root. The trie type isn't exported, so it is
+    /// deserialized.
+    fn unresolved_trie<T: serde::de::DeserializeOwned>() -> T {
+        serde_json::from_value(serde_json::json!({ "data": { "Digest": B256::repeat_byte(1) } }))
+            .unwrap()
+    }
+
+    fn validation_error(input: &ClientExecutorInput) -> String {
+        input.validate_completeness().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_validate_completeness() {
+        complete_input().validate_completeness().unwrap();
+    }
+
+    #[test]
+    fn test_validate_completeness_missing_parent_header() {
+        let mut input = complete_input();
+        input.ancestor_headers.clear();
+        assert!(validation_error(&input).contains("missing parent header"));
+    }
+
+    #[test]
+    fn test_validate_completeness_unresolved_account() {
+        let mut input = complete_input();
+        input.parent_state.state_trie = unresolved_trie();
+        assert!(validation_error(&input).contains("failed to read account"));
+    }
+
+    #[test]
+    fn test_validate_completeness_missing_bytecode() {
+        let mut input = complete_input();
+        input.bytecodes.clear();
+        assert!(validation_error(&input).contains("missing bytecode for account"));
+    }
+
+    #[test]
+    fn test_validate_completeness_missing_storage_trie() {
+        let mut input = complete_input();
+        input.parent_state.storage_tries.clear();
+        assert!(validation_error(&input).contains("missing storage trie for account"));
+    }
+
+    #[test]
+    fn test_validate_completeness_unresolved_storage_slot() {
+        let mut input = complete_input();
+        let hashed_address = keccak256(Address::repeat_byte(1));
+        input.parent_state.storage_tries.insert(hashed_address, unresolved_trie());
+        assert!(validation_error(&input).contains("failed to read storage slot"));
+    }
+
+    #[test]
+    fn test_to_execution_witness() {
+        let code = |byte: u8| Bytecode::new_raw(Bytes::from(vec![0x60, byte]));
+        let mut input = complete_input();
+        input.bytecodes = vec![code(2), code(1), code(2)];
+        input.ancestor_headers =
+            (1..=3).rev().map(|number| Header { number, ..Default::default() }).collect();
+
+        let witness = input.to_execution_witness();
+        assert_eq!(witness.state, input.parent_state.trie_nodes());
+
+        // The codes are deduplicated and sorted by hash.
+        let mut codes = vec![code(1), code(2)];
+        codes.sort_by_key(|code| code.hash_slow());
+        assert_eq!(witness.codes, codes.iter().map(|code| code.original_bytes()).collect_vec());
+
+        // The headers stay in ancestor order, starting from the parent block.
+        let numbers = witness
+            .headers
+            .iter()
+            .map(|header| Header::decode(&mut &header[..]).unwrap().number)
+            .collect_vec();
+        assert_eq!(numbers, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_access_list() {
+        let mut input = complete_input();
+        input.state_requests = HashMap::from([
+            (Address::repeat_byte(3), vec![U256::from(2), U256::from(1)]),
+            (Address::repeat_byte(1), vec![]),
+            (Address::repeat_byte(2), vec![U256::from(3)]),
+        ]);
+
+        // The accounts are sorted by address, and their slots keep the order of the state requests.
+        let AccessList(access_list) = input.access_list();
+        let addresses = access_list.iter().map(|item| item.address).collect_vec();
+        assert_eq!(addresses, [1, 2, 3].map(Address::repeat_byte));
+        assert!(access_list[0].storage_keys.is_empty());
+        assert_eq!(access_list[1].storage_keys, vec![B256::from(U256::from(3))]);
+        assert_eq!(
+            access_list[2].storage_keys,
+            vec![B256::from(U256::from(2)), B256::from(U256::from(1))]
+        );
+    }
+}
@@ -9,7 +9,7 @@ use std::{borrow::BorrowMut, fmt::Display};
 
 use custom::CustomEvmConfig;
 use eyre::eyre;
-use io::ClientExecutorInput;
+use io::{ClientExecutorInput, ClientExecutorOptions};
 use reth_chainspec::ChainSpec;
 use reth_errors::ProviderError;
 use reth_ethereum_consensus::validate_block_post_execution as validate_block_post_execution_ethereum;
@@ -18,9 +18,11 @@ use reth_evm_ethereum::execute::EthExecutorProvider;
 use reth_evm_optimism::OpExecutorProvider;
 use reth_execution_types::ExecutionOutcome;
 use reth_optimism_consensus::validate_block_post_execution as validate_block_post_execution_optimism;
-use reth_primitives::{proofs, Block, BlockWithSenders, Bloom, Header, Receipt, Receipts, Request};
+use reth_primitives::{
+    proofs, Block, BlockWithSenders, Bloom, Header, Receipt, Receipts, Request, B256,
+};
 use revm::{db::CacheDB, Database};
-use revm_primitives::{address, U256};
+use revm_primitives::{address, calc_excess_blob_gas, U256};
 
 /// Chain ID for Ethereum Mainnet.
 pub const CHAIN_ID_ETH_MAINNET: u64 = 0x1;
@@ -44,6 +46,7 @@ pub trait Variant {
         executor_block_input: &BlockWithSenders,
         executor_difficulty: U256,
         cache_db: DB,
+        blob_base_fee_override: Option<u128>,
     ) -> eyre::Result<BlockExecutionOutput<Receipt>>
     where
         DB: Database<Error: Into<ProviderError> + Display>;
@@ -92,13 +95,99 @@ impl ChainVariant {
             ChainVariant::Linea => CHAIN_ID_LINEA_MAINNET,
         }
     }
+
+    /// Returns the canonical hash of the genesis block of the network of the given variant, e.g. to
+    /// check that an RPC node serves the expected chain.
+    pub fn genesis_hash(&self) -> B256 {
+        match self {
+            ChainVariant::Ethereum => EthereumVariant::spec().genesis_hash(),
+            ChainVariant::Optimism => OptimismVariant::spec().genesis_hash(),
+            ChainVariant::Linea => LineaVariant::spec().genesis_hash(),
+        }
+    }
+
+    /// Returns the name of the network of the given variant.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainVariant::Ethereum => "Ethereum Mainnet",
+            ChainVariant::Optimism => "OP Mainnet",
+            ChainVariant::Linea => "Linea Mainnet",
+        }
+    }
+}
+
+/// Returns the chain IDs and names of the chains supported out of the box.
+pub fn supported_chains() -> Vec<(u64, &'static str)> {
+    [ChainVariant::Ethereum, ChainVariant::Optimism, ChainVariant::Linea]
+        .iter()
+        .map(|variant| (variant.chain_id(), variant.name()))
+        .collect()
+}
+
+/// Accumulates the logs bloom of a block from the receipts of its transactions. This is shared by
+/// the host and the client so that both derive the same header.
+pub fn accrue_logs_bloom(receipts: &[Receipt]) -> Bloom {
+    let mut logs_bloom = Bloom::default();
+    receipts.iter().for_each(|r| {
+        logs_bloom.accrue_bloom(&r.bloom_slow());
+    });
+    logs_bloom
+}
+
+/// Derives the excess blob gas of the child of a block from its header, as specified by EIP-4844.
+/// The blob gas fields are missing from the parent of the Cancun fork block, and count as 0.
+pub fn next_block_excess_blob_gas(parent: &Header) -> u64 {
+    calc_excess_blob_gas(
+        parent.excess_blob_gas.unwrap_or_default(),
+        parent.blob_gas_used.unwrap_or_default(),
+    )
+}
+
+/// Returns the EVM configuration of a chain variant, with the blob base fee overridden if set.
+fn evm_config(variant: ChainVariant, blob_base_fee_override: Option<u128>) -> CustomEvmConfig {
+    let evm_config = CustomEvmConfig::from_variant(variant);
+    match blob_base_fee_override {
+        Some(blob_base_fee) => evm_config.with_blob_base_fee_override(blob_base_fee),
+        None => evm_config,
+    }
 }
 
 impl ClientExecutor {
-    pub fn execute<V>(&self, mut input: ClientExecutorInput) -> eyre::Result<Header>
+    pub fn execute<V>(&self, input: ClientExecutorInput) -> eyre::Result<Header>
     where
         V: Variant,
     {
+        self.execute_with_options::<V>(input, &ClientExecutorOptions::default())
+    }
+
+    /// Executes the block like [`ClientExecutor::execute`], with additional checks enabled by the
+    /// [`ClientExecutorOptions`].
+    pub fn execute_with_options<V>(
+        &self,
+        mut input: ClientExecutorInput,
+        options: &ClientExecutorOptions,
+    ) -> eyre::Result<Header>
+    where
+        V: Variant,
+    {
+        // Check that the input is complete before doing any work. This is only done in debug
+        // builds as `witness_db` catches the same issues, albeit with less precise messages.
+        #[cfg(debug_assertions)]
+        profile!("validate input completeness", { input.validate_completeness() })?;
+
+        // Verify the excess blob gas against the parent header, so that the host can't feed wrong
+        // blob gas accounting into the committed header. Blocks before Cancun have none.
+        if let Some(excess_blob_gas) = input.current_block.header.excess_blob_gas {
+            let expected = next_block_excess_blob_gas(input.parent_header());
+            if excess_blob_gas != expected {
+                eyre::bail!(
+                    "mismatched excess blob gas: expected={}, header={}",
+                    expected,
+                    excess_blob_gas
+                );
+            }
+        }
+
         // Initialize the witnessed database with verified storage proofs.
         let witness_db = input.witness_db()?;
         let cache_db = CacheDB::new(&witness_db);
@@ -114,26 +203,44 @@ impl ClientExecutor {
         })?;
         let executor_difficulty = input.current_block.header.difficulty;
         let executor_output = profile!("execute", {
-            V::execute(&executor_block_input, executor_difficulty, cache_db)
+            V::execute(&executor_block_input, executor_difficulty, cache_db, None)
         })?;
 
-        // Validate the block post execution.
-        profile!("validate block post-execution", {
-            V::validate_block_post_execution(
-                &executor_block_input,
-                &spec,
-                &executor_output.receipts,
-                &executor_output.requests,
-            )
-        })?;
+        // Validate the block post execution, unless explicitly skipped for benchmarking.
+        if !options.skip_post_execution_validation {
+            profile!("validate block post-execution", {
+                V::validate_block_post_execution(
+                    &executor_block_input,
+                    &spec,
+                    &executor_output.receipts,
+                    &executor_output.requests,
+                )
+            })?;
+        }
+
+        // Verify the gas used, if requested.
+        //
+        // Note: this is already covered by `validate_block_post_execution`, but checking it
+        // explicitly helps with diagnosing custom chains.
+        if options.verify_gas_used {
+            let gas_used = executor_output
+                .receipts
+                .last()
+                .map(|receipt| receipt.cumulative_gas_used)
+                .unwrap_or_default();
+
+            if gas_used != input.current_block.header.gas_used {
+                eyre::bail!(
+                    "mismatched gas used: executed={}, header={}",
+                    gas_used,
+                    input.current_block.header.gas_used
+                );
+            }
+        }
 
         // Accumulate the logs bloom.
-        let mut logs_bloom = Bloom::default();
-        profile!("accrue logs bloom", {
-            executor_output.receipts.iter().for_each(|r| {
-                logs_bloom.accrue_bloom(&r.bloom_slow());
-            })
-        });
+        let logs_bloom =
+            profile!("accrue logs bloom", { accrue_logs_bloom(&executor_output.receipts) });
 
         // Convert the output to an execution outcome.
         let executor_outcome = ExecutionOutcome::new(
@@ -145,9 +252,12 @@ impl ClientExecutor {
 
         // Verify the state root.
         let state_root = profile!("compute state root", {
-            input.parent_state.update(&executor_outcome.hash_state_slow());
-            input.parent_state.state_root()
-        });
+            input
+                .parent_state
+                .update(&executor_outcome.hash_state_slow())
+                .map(|_| input.parent_state.state_root())
+        })
+        .map_err(|err| eyre!("incomplete state after update: {}", err))?;
 
         if state_root != input.current_block.state_root {
             eyre::bail!("mismatched state root");
@@ -159,7 +269,7 @@ impl ClientExecutor {
         let mut header = input.current_block.header.clone();
         header.parent_hash = input.parent_header().hash_slow();
         header.ommers_hash = proofs::calculate_ommers_root(&input.current_block.ommers);
-        header.state_root = input.current_block.state_root;
+        header.state_root = state_root;
         header.transactions_root = proofs::calculate_transaction_root(&input.current_block.body);
         header.receipts_root = input.current_block.header.receipts_root;
         header.withdrawals_root = input
@@ -173,6 +283,88 @@ impl ClientExecutor {
 
         Ok(header)
     }
+
+    /// Executes the block and returns the resulting state root, without validating the block or
+    /// deriving its header. This is meant for tooling that only cares about the root, e.g. fuzzing.
+    pub fn compute_post_state_root<V>(&self, input: ClientExecutorInput) -> eyre::Result<B256>
+    where
+        V: Variant,
+    {
+        self.compute_post_state_root_with_overrides::<V>(input, None)
+    }
+
+    /// Executes the block with the given blob base fee instead of the one derived from its excess
+    /// blob gas, and returns the resulting state root, e.g. to replay a block under other blob fee
+    /// conditions. Nothing is validated, as the result generally doesn't match the actual block,
+    /// so this is only run natively on the host and never inside the zkVM.
+    pub fn execute_with_blob_base_fee<V>(
+        &self,
+        input: ClientExecutorInput,
+        blob_base_fee: u128,
+    ) -> eyre::Result<B256>
+    where
+        V: Variant,
+    {
+        self.compute_post_state_root_with_overrides::<V>(input, Some(blob_base_fee))
+    }
+
+    /// Executes the block with the given overrides of its EVM environment and returns the
+    /// resulting state root.
+    fn compute_post_state_root_with_overrides<V>(
+        &self,
+        mut input: ClientExecutorInput,
+        blob_base_fee_override: Option<u128>,
+    ) -> eyre::Result<B256>
+    where
+        V: Variant,
+    {
+        // Initialize the witnessed database with verified storage proofs.
+        let witness_db = input.witness_db()?;
+        let cache_db = CacheDB::new(&witness_db);
+
+        // Execute the block.
+        let executor_block_input = input
+            .current_block
+            .clone()
+            .with_recovered_senders()
+            .ok_or(eyre!("failed to recover senders"))?;
+        let executor_difficulty = input.current_block.header.difficulty;
+        let executor_output = V::execute(
+            &executor_block_input,
+            executor_difficulty,
+            cache_db,
+            blob_base_fee_override,
+        )?;
+
+        // Apply the state changes to the parent state.
+        let executor_outcome = ExecutionOutcome::new(
+            executor_output.state,
+            Receipts::from(executor_output.receipts),
+            input.current_block.header.number,
+            vec![executor_output.requests.into()],
+        );
+        input
+            .parent_state
+            .update(&executor_outcome.hash_state_slow())
+            .map_err(|err| eyre!("incomplete state after update: {}", err))?;
+
+        Ok(input.parent_state.state_root())
+    }
+
+    /// Executes only the first `tx_count` transactions of the block and returns the intermediate
+    /// state root, without validating anything. This is a debugging tool for the host, and is never
+    /// run inside the zkVM since the result doesn't correspond to any block.
+    pub fn execute_up_to_tx<V>(
+        &self,
+        mut input: ClientExecutorInput,
+        tx_count: usize,
+    ) -> eyre::Result<B256>
+    where
+        V: Variant,
+    {
+        input.current_block.body.truncate(tx_count);
+        self.compute_post_state_root::<V>(input)
+    }
 }
 
 impl Variant for EthereumVariant {
@@ -184,13 +376,14 @@ impl Variant for EthereumVariant {
         executor_block_input: &BlockWithSenders,
         executor_difficulty: U256,
         cache_db: DB,
+        blob_base_fee_override: Option<u128>,
     ) -> eyre::Result<BlockExecutionOutput<Receipt>>
     where
         DB: Database<Error: Into<ProviderError> + Display>,
     {
         Ok(EthExecutorProvider::new(
             Self::spec().into(),
-            CustomEvmConfig::from_variant(ChainVariant::Ethereum),
+            evm_config(ChainVariant::Ethereum, blob_base_fee_override),
         )
         .executor(cache_db)
         .execute((executor_block_input, executor_difficulty).into())?)
@@ -215,13 +408,14 @@ impl Variant for OptimismVariant {
         executor_block_input: &BlockWithSenders,
         executor_difficulty: U256,
         cache_db: DB,
+        blob_base_fee_override: Option<u128>,
     ) -> eyre::Result<BlockExecutionOutput<Receipt>>
     where
         DB: Database<Error: Into<ProviderError> + Display>,
     {
         Ok(OpExecutorProvider::new(
             Self::spec().into(),
-            CustomEvmConfig::from_variant(ChainVariant::Optimism),
+            evm_config(ChainVariant::Optimism, blob_base_fee_override),
         )
         .executor(cache_db)
         .execute((executor_block_input, executor_difficulty).into())?)
@@ -246,13 +440,14 @@ impl Variant for LineaVariant {
         executor_block_input: &BlockWithSenders,
         executor_difficulty: U256,
         cache_db: DB,
+        blob_base_fee_override: Option<u128>,
     ) -> eyre::Result<BlockExecutionOutput<Receipt>>
     where
         DB: Database<Error: Into<ProviderError> + Display>,
     {
         Ok(EthExecutorProvider::new(
             Self::spec().into(),
-            CustomEvmConfig::from_variant(ChainVariant::Linea),
+            evm_config(ChainVariant::Linea, blob_base_fee_override),
         )
         .executor(cache_db)
         .execute((executor_block_input, executor_difficulty).into())?)
@@ -286,3 +481,64 @@ impl Variant for LineaVariant {
         block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{b256, logs_bloom, Address, Bytes, Log, TxType};
+
+    #[test]
+    fn test_accrue_logs_bloom() {
+        let log = |byte: u8| {
+            Log::new_unchecked(
+                Address::repeat_byte(byte),
+                vec![B256::repeat_byte(byte)],
+                Bytes::from(vec![byte]),
+            )
+        };
+        let receipts = vec![
+            Receipt { tx_type: TxType::Legacy, logs: vec![log(1), log(2)], ..Default::default() },
+            Receipt { tx_type: TxType::Eip1559, logs: vec![], ..Default::default() },
+            Receipt { tx_type: TxType::Eip1559, logs: vec![log(3)], ..Default::default() },
+        ];
+
+        let expected = logs_bloom(receipts.iter().flat_map(|receipt| &receipt.logs));
+        assert_ne!(expected, Bloom::default());
+        assert_eq!(accrue_logs_bloom(&receipts), expected);
+        assert_eq!(accrue_logs_bloom(&[]), Bloom::default());
+    }
+
+    #[test]
+    fn test_genesis_hash() {
+        assert_eq!(
+            ChainVariant::Ethereum.genesis_hash(),
+            b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3")
+        );
+        assert_eq!(
+            ChainVariant::Optimism.genesis_hash(),
+            b256!("7ca38a1916c42007829c55e69d3e9a73265554b586a499015373241b8a3fa48b")
+        );
+        assert_eq!(
+            ChainVariant::Linea.genesis_hash(),
+            b256!("b6762a65689107b2326364aefc18f94cda413209fab35c00d4af51eaa20ffbc6")
+        );
+    }
+
+    #[test]
+    fn test_next_block_excess_blob_gas() {
+        // The parent of the Cancun fork block has no blob gas fields.
+        assert_eq!(next_block_excess_blob_gas(&Header::default()), 0);
+
+        let target = revm_primitives::TARGET_BLOB_GAS_PER_BLOCK;
+        let parent = Header {
+            excess_blob_gas: Some(target),
+            blob_gas_used: Some(target * 2),
+            ..Default::default()
+        };
+        assert_eq!(next_block_excess_blob_gas(&parent), target * 2);
+
+        let parent =
+            Header { excess_blob_gas: Some(0), blob_gas_used: Some(target), ..Default::default() };
+        assert_eq!(next_block_excess_blob_gas(&parent), 0);
+    }
+}
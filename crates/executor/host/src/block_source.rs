@@ -0,0 +1,85 @@
+use std::marker::PhantomData;
+
+use alloy_provider::{network::AnyNetwork, Provider};
+use alloy_transport::Transport;
+use async_trait::async_trait;
+use eyre::eyre;
+use reth_primitives::{Block, Header, B256};
+
+/// A source of blocks and headers used by the [`HostExecutor`](crate::HostExecutor).
+///
+/// State is always fetched from the provider, but blocks and headers can be read from any source
+/// that implements this trait (e.g. local history files for bulk historical proving).
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Fetches the full block with the given block number.
+    async fn block(&self, block_number: u64) -> eyre::Result<Block>;
+
+    /// Fetches the header of the block with the given block number.
+    async fn header(&self, block_number: u64) -> eyre::Result<Header>;
+}
+
+/// A [`BlockSource`] that fetches blocks from a [Provider].
+#[derive(Debug, Clone)]
+pub struct RpcBlockSource<T, P> {
+    /// The provider which fetches data.
+    pub provider: P,
+    /// A phantom type to make the struct generic over the transport.
+    pub phantom: PhantomData<T>,
+}
+
+impl<T, P> RpcBlockSource<T, P> {
+    /// Create a new [`RpcBlockSource`] with a specific [Provider].
+    pub fn new(provider: P) -> Self {
+        Self { provider, phantom: PhantomData }
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> BlockSource
+    for RpcBlockSource<T, P>
+{
+    async fn block(&self, block_number: u64) -> eyre::Result<Block> {
+        let block = self
+            .provider
+            .get_block_by_number(block_number.into(), true)
+            .await?
+            .ok_or(eyre!("couldn't fetch block: {}", block_number))?;
+
+        let expected_hash = block.inner.header.hash;
+        let block = Block::try_from(block.inner)?;
+        verify_header_hash(&block.header, expected_hash)?;
+
+        Ok(block)
+    }
+
+    async fn header(&self, block_number: u64) -> eyre::Result<Header> {
+        let block = self
+            .provider
+            .get_block_by_number(block_number.into(), false)
+            .await?
+            .ok_or(eyre!("couldn't fetch block: {}", block_number))?;
+
+        let expected_hash = block.inner.header.hash;
+        let header = Header::try_from(block.inner.header)?;
+        verify_header_hash(&header, expected_hash)?;
+
+        Ok(header)
+    }
+}
+
+/// Checks that a header converted from an RPC response hashes to the hash reported by the RPC
+/// node, which catches fields that are missing or wrongly mapped in the conversion.
+fn verify_header_hash(header: &Header, expected_hash: B256) -> eyre::Result<()> {
+    let hash = header.hash_slow();
+    if hash != expected_hash {
+        eyre::bail!(
+            "mismatched header hash: block_number={}, expected={}, got={}",
+            header.number,
+            expected_hash,
+            hash
+        );
+    }
+
+    Ok(())
+}
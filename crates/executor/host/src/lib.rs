@@ -1,23 +1,59 @@
-use std::{collections::BTreeSet, marker::PhantomData};
+use std::{collections::BTreeSet, future::Future, iter::once, marker::PhantomData, pin::pin};
 
 use alloy_provider::{network::AnyNetwork, Provider};
-use alloy_transport::Transport;
+use alloy_rpc_types::{BlockTransactionsKind, EIP1186AccountProofResponse};
+use alloy_transport::{Transport, TransportError};
 use eyre::{eyre, Ok};
+use futures::future::{select, Either};
+use itertools::Itertools;
 use reth_execution_types::ExecutionOutcome;
-use reth_primitives::{proofs, Block, Bloom, Receipts, B256};
+use reth_primitives::{proofs, Address, Receipts, B256, U256};
 use revm::db::CacheDB;
+use revm_primitives::HashMap;
 use rsp_client_executor::{
-    io::ClientExecutorInput, ChainVariant, EthereumVariant, LineaVariant, OptimismVariant, Variant,
+    accrue_logs_bloom, io::ClientExecutorInput, ChainVariant, EthereumVariant, LineaVariant,
+    OptimismVariant, Variant,
 };
 use rsp_mpt::EthereumState;
 use rsp_primitives::account_proof::eip1186_proof_to_account_proof;
-use rsp_rpc_db::RpcDb;
+use rsp_rpc_db::{RetryBudget, RetryError, RpcDb};
+use tokio_util::sync::CancellationToken;
+use tracing::{field, Instrument};
+
+mod block_source;
+pub use block_source::{BlockSource, RpcBlockSource};
+
+/// Fragments of the errors returned by common RPC nodes when they can't serve historical state,
+/// in lowercase.
+const MISSING_STATE_ERRORS: &[&str] =
+    &["missing trie node", "state not available", "is not available", "pruned", "proof window"];
 
 /// An executor that fetches data from a [Provider] to execute blocks in the [ClientExecutor].
 #[derive(Debug, Clone)]
-pub struct HostExecutor<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> {
+pub struct HostExecutor<
+    T: Transport + Clone,
+    P: Provider<T, AnyNetwork> + Clone,
+    B: BlockSource = RpcBlockSource<T, P>,
+> {
     /// The provider which fetches data.
     pub provider: P,
+    /// The source of blocks and headers.
+    pub block_source: B,
+    /// The maximum number of storage keys requested in a single `eth_getProof` call. Larger key
+    /// sets are split over several calls, for providers that reject large requests.
+    pub max_keys_per_proof: Option<usize>,
+    /// If set, at least this many ancestor headers are included in the client input, even when
+    /// the block doesn't use `BLOCKHASH` that far back.
+    pub force_ancestor_window: Option<u64>,
+    /// If set, the requests of a block that fail with a transient error are retried up to this
+    /// many times in total, with an exponential backoff.
+    pub retry_budget: Option<u32>,
+    /// Fragments of the error messages returned by the node that are retried like transport
+    /// failures, e.g. "header not found" for providers that briefly fail to serve the chain tip.
+    pub retryable_errors: Vec<String>,
+    /// If set, the storage slots of these accounts are fetched ahead of execution, with a single
+    /// proof request per account instead of one request per slot.
+    pub access_list: Option<HashMap<Address, Vec<U256>>>,
     /// A phantom type to make the struct generic over the transport.
     pub phantom: PhantomData<T>,
 }
@@ -25,7 +61,59 @@ pub struct HostExecutor<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone
 impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P> {
     /// Create a new [`HostExecutor`] with a specific [Provider] and [Transport].
     pub fn new(provider: P) -> Self {
-        Self { provider, phantom: PhantomData }
+        Self::with_block_source(provider.clone(), RpcBlockSource::new(provider))
+    }
+}
+
+impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone, B: BlockSource>
+    HostExecutor<T, P, B>
+{
+    /// Create a new [`HostExecutor`] that reads blocks and headers from a [`BlockSource`] while
+    /// fetching state from the [Provider].
+    pub fn with_block_source(provider: P, block_source: B) -> Self {
+        Self {
+            provider,
+            block_source,
+            max_keys_per_proof: None,
+            force_ancestor_window: None,
+            retry_budget: None,
+            retryable_errors: vec![],
+            access_list: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the maximum number of storage keys requested in a single `eth_getProof` call.
+    pub fn with_max_keys_per_proof(mut self, max_keys_per_proof: usize) -> Self {
+        self.max_keys_per_proof = Some(max_keys_per_proof);
+        self
+    }
+
+    /// Sets the minimum number of ancestor headers included in the client input.
+    pub fn with_force_ancestor_window(mut self, force_ancestor_window: u64) -> Self {
+        self.force_ancestor_window = Some(force_ancestor_window);
+        self
+    }
+
+    /// Sets the total number of retries of failed requests allowed for a block, shared by the
+    /// block, header and state fetches.
+    pub fn with_retry_budget(mut self, retry_budget: u32) -> Self {
+        self.retry_budget = Some(retry_budget);
+        self
+    }
+
+    /// Sets the fragments of the error messages returned by the node that are retried within the
+    /// retry budget, case-insensitively.
+    pub fn with_retryable_errors(mut self, retryable_errors: Vec<String>) -> Self {
+        self.retryable_errors = retryable_errors;
+        self
+    }
+
+    /// Sets the storage slots to fetch ahead of execution, e.g. the state requests of a previous
+    /// run of the block. Slots left unused by the block are not included in the client input.
+    pub fn with_access_list(mut self, access_list: HashMap<Address, Vec<U256>>) -> Self {
+        self.access_list = Some(access_list);
+        self
     }
 
     /// Executes the block with the given block number.
@@ -34,33 +122,141 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
         block_number: u64,
         variant: ChainVariant,
     ) -> eyre::Result<ClientExecutorInput> {
-        let client_input = match variant {
-            ChainVariant::Ethereum => self.execute_variant::<EthereumVariant>(block_number).await,
-            ChainVariant::Optimism => self.execute_variant::<OptimismVariant>(block_number).await,
-            ChainVariant::Linea => self.execute_variant::<LineaVariant>(block_number).await,
-        }?;
+        self.execute_with_cancellation(block_number, variant, CancellationToken::new()).await
+    }
+
+    /// Executes the block with the given block number, aborting as soon as the
+    /// [CancellationToken] is cancelled.
+    ///
+    /// Pending RPC round trips are dropped on cancellation, including the state fetches made from
+    /// within block execution.
+    pub async fn execute_with_cancellation(
+        &self,
+        block_number: u64,
+        variant: ChainVariant,
+        cancellation_token: CancellationToken,
+    ) -> eyre::Result<ClientExecutorInput> {
+        self.execute_block(block_number, None, variant, cancellation_token).await
+    }
+
+    /// Executes the block with the given hash. Unlike [`Self::execute`], this fails if the block
+    /// at that height isn't the requested one anymore, e.g. after a reorg.
+    pub async fn execute_by_hash(
+        &self,
+        block_hash: B256,
+        variant: ChainVariant,
+    ) -> eyre::Result<ClientExecutorInput> {
+        let block_number = self.block_number_by_hash(block_hash).await?;
+        self.execute_block(block_number, Some(block_hash), variant, CancellationToken::new()).await
+    }
+
+    /// Returns the number of the block with the given hash.
+    pub async fn block_number_by_hash(&self, block_hash: B256) -> eyre::Result<u64> {
+        let block = request(&self.new_retry_budget(), || async {
+            Ok(self.provider.get_block_by_hash(block_hash, BlockTransactionsKind::Hashes).await?)
+        })
+        .await?
+        .ok_or(eyre!("couldn't fetch block: {}", block_hash))?;
+
+        Ok(block.header.number)
+    }
+
+    async fn execute_block(
+        &self,
+        block_number: u64,
+        expected_block_hash: Option<B256>,
+        variant: ChainVariant,
+        cancellation_token: CancellationToken,
+    ) -> eyre::Result<ClientExecutorInput> {
+        // Tag the logs of the execution with the block, so that the logs of concurrent executions
+        // can be told apart. The hash and gas used are recorded once the block is fetched.
+        let span = tracing::info_span!(
+            "execute_block",
+            chain_id = variant.chain_id(),
+            block_number,
+            block_hash = field::Empty,
+            gas_used = field::Empty
+        );
+
+        let execution = pin!(async {
+            match variant {
+                ChainVariant::Ethereum => {
+                    self.execute_variant::<EthereumVariant>(
+                        block_number,
+                        expected_block_hash,
+                        &cancellation_token,
+                    )
+                    .await
+                }
+                ChainVariant::Optimism => {
+                    self.execute_variant::<OptimismVariant>(
+                        block_number,
+                        expected_block_hash,
+                        &cancellation_token,
+                    )
+                    .await
+                }
+                ChainVariant::Linea => {
+                    self.execute_variant::<LineaVariant>(
+                        block_number,
+                        expected_block_hash,
+                        &cancellation_token,
+                    )
+                    .await
+                }
+            }
+        }
+        .instrument(span));
+        let cancelled = pin!(cancellation_token.cancelled());
+
+        let client_input = match select(execution, cancelled).await {
+            Either::Left((client_input, _)) => client_input?,
+            Either::Right(_) => eyre::bail!("cancelled: block_number={}", block_number),
+        };
 
         Ok(client_input)
     }
 
-    async fn execute_variant<V>(&self, block_number: u64) -> eyre::Result<ClientExecutorInput>
+    async fn execute_variant<V>(
+        &self,
+        block_number: u64,
+        expected_block_hash: Option<B256>,
+        cancellation_token: &CancellationToken,
+    ) -> eyre::Result<ClientExecutorInput>
     where
         V: Variant,
     {
-        // Fetch the current block and the previous block from the provider.
-        tracing::info!("fetching the current block and the previous block");
-        let current_block = self
-            .provider
-            .get_block_by_number(block_number.into(), true)
-            .await?
-            .map(|block| Block::try_from(block.inner))
-            .ok_or(eyre!("couldn't fetch block: {}", block_number))??;
-        let previous_block = self
-            .provider
-            .get_block_by_number((block_number - 1).into(), true)
-            .await?
-            .map(|block| Block::try_from(block.inner))
-            .ok_or(eyre!("couldn't fetch block: {}", block_number))??;
+        // All the requests made for the block share a single retry budget.
+        let retry_budget = self.new_retry_budget();
+
+        // Fetch the current block and the header of the previous block from the provider. Only the
+        // header of the previous block is needed, so its body is not fetched.
+        tracing::info!("fetching the current block and the previous header");
+        let current_block =
+            request(&retry_budget, || self.block_source.block(block_number)).await?;
+        let previous_header =
+            request(&retry_budget, || self.block_source.header(block_number - 1)).await?;
+
+        // Make sure the block is the requested one when it was requested by hash.
+        if let Some(expected_block_hash) = expected_block_hash {
+            let block_hash = current_block.header.hash_slow();
+            if block_hash != expected_block_hash {
+                eyre::bail!(
+                    "mismatched block hash, the block may have been reorged: expected={}, got={}",
+                    expected_block_hash,
+                    block_hash
+                );
+            }
+        }
+
+        // Record the fetched block in the execution span.
+        tracing::Span::current()
+            .record("block_hash", field::display(current_block.header.hash_slow()))
+            .record("gas_used", current_block.header.gas_used);
+
+        // Check that the parent state can be proven before doing any work.
+        tracing::info!("checking that the provider can serve the parent state");
+        self.ensure_state_available(block_number - 1, &retry_budget).await?;
 
         // Setup the spec for the block executor.
         tracing::info!("setting up the spec for the block executor");
@@ -68,7 +264,19 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
 
         // Setup the database for the block executor.
         tracing::info!("setting up the database for the block executor");
-        let rpc_db = RpcDb::new(self.provider.clone(), block_number - 1);
+        let mut rpc_db = RpcDb::new(self.provider.clone(), block_number - 1)
+            .with_cancellation_token(cancellation_token.clone())
+            .with_retry_budget(retry_budget.clone());
+        if let Some(force_ancestor_window) = self.force_ancestor_window {
+            rpc_db = rpc_db.with_ancestor_window(force_ancestor_window);
+        }
+        if let Some(max_keys_per_proof) = self.max_keys_per_proof {
+            rpc_db = rpc_db.with_max_keys_per_proof(max_keys_per_proof);
+        }
+        if let Some(access_list) = &self.access_list {
+            tracing::info!("prefetching the storage of {} accounts", access_list.len());
+            rpc_db.prefetch_storage(access_list).await?;
+        }
         let cache_db = CacheDB::new(&rpc_db);
 
         // Execute the block and fetch all the necessary data along the way.
@@ -82,7 +290,8 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
             .with_recovered_senders()
             .ok_or(eyre!("failed to recover senders"))?;
         let executor_difficulty = current_block.header.difficulty;
-        let executor_output = V::execute(&executor_block_input, executor_difficulty, cache_db)?;
+        let executor_output =
+            V::execute(&executor_block_input, executor_difficulty, cache_db, None)?;
 
         // Validate the block post execution.
         tracing::info!("validating the block post execution");
@@ -95,10 +304,7 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
 
         // Accumulate the logs bloom.
         tracing::info!("accumulating the logs bloom");
-        let mut logs_bloom = Bloom::default();
-        executor_output.receipts.iter().for_each(|r| {
-            logs_bloom.accrue_bloom(&r.bloom_slow());
-        });
+        let logs_bloom = accrue_logs_bloom(&executor_output.receipts);
 
         // Convert the output to an execution outcome.
         let executor_outcome = ExecutionOutcome::new(
@@ -135,23 +341,19 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
                 .into_iter()
                 .collect::<Vec<_>>();
 
-            let storage_proof = self
-                .provider
-                .get_proof(*address, keys.clone())
-                .block_id((block_number - 1).into())
-                .await?;
+            let storage_proof =
+                self.fetch_proof(*address, &keys, block_number - 1, &retry_budget).await?;
             before_storage_proofs.push(eip1186_proof_to_account_proof(storage_proof));
 
-            let storage_proof = self
-                .provider
-                .get_proof(*address, modified_keys)
-                .block_id((block_number).into())
-                .await?;
+            let storage_proof =
+                self.fetch_proof(*address, &modified_keys, block_number, &retry_budget).await?;
             after_storage_proofs.push(eip1186_proof_to_account_proof(storage_proof));
         }
 
+        // The proofs are checked against the parent state root, so that a faulty state
+        // reconstruction is not reported as a post-execution state root mismatch.
         let state = EthereumState::from_transition_proofs(
-            previous_block.state_root,
+            previous_header.state_root,
             &before_storage_proofs.iter().map(|item| (item.address, item.clone())).collect(),
             &after_storage_proofs.iter().map(|item| (item.address, item.clone())).collect(),
         )?;
@@ -160,7 +362,7 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
         tracing::info!("verifying the state root");
         let state_root = {
             let mut mutated_state = state.clone();
-            mutated_state.update(&executor_outcome.hash_state_slow());
+            mutated_state.update(&executor_outcome.hash_state_slow())?;
             mutated_state.state_root()
         };
         if state_root != current_block.state_root {
@@ -171,7 +373,7 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
         //
         // Note: the receipts root and gas used are verified by `validate_block_post_execution`.
         let mut header = current_block.header.clone();
-        header.parent_hash = previous_block.hash_slow();
+        header.parent_hash = previous_header.hash_slow();
         header.ommers_hash = proofs::calculate_ommers_root(&current_block.ommers);
         header.state_root = current_block.state_root;
         header.transactions_root = proofs::calculate_transaction_root(&current_block.body);
@@ -200,8 +402,25 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
         let mut ancestor_headers = vec![];
         tracing::info!("fetching {} ancestor headers", block_number - oldest_ancestor);
         for height in (oldest_ancestor..=(block_number - 1)).rev() {
-            let block = self.provider.get_block_by_number(height.into(), false).await?.unwrap();
-            ancestor_headers.push(block.inner.header.try_into()?);
+            ancestor_headers
+                .push(request(&retry_budget, || self.block_source.header(height)).await?);
+        }
+
+        // Verify that the ancestor headers form a chain, which the client would otherwise only
+        // find out inside the zkVM.
+        tracing::info!("verifying the ancestor headers");
+        for (child_header, parent_header) in
+            once(&current_block.header).chain(ancestor_headers.iter()).tuple_windows()
+        {
+            if parent_header.number != child_header.number - 1
+                || parent_header.hash_slow() != child_header.parent_hash
+            {
+                eyre::bail!(
+                    "non-contiguous ancestor headers: child_number={}, parent_number={}",
+                    child_header.number,
+                    parent_header.number
+                );
+            }
         }
 
         // Create the client input.
@@ -216,4 +435,102 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> HostExecutor<T, P
 
         Ok(client_input)
     }
+
+    /// Checks that the provider can serve state proofs at the given block, returning a clear error
+    /// when it's not an archive node and the block is too old.
+    async fn ensure_state_available(
+        &self,
+        block_number: u64,
+        retry_budget: &RetryBudget,
+    ) -> eyre::Result<()> {
+        let Err(err) = request(retry_budget, || async {
+            Ok(self.provider.get_proof(Address::ZERO, vec![]).block_id(block_number.into()).await?)
+        })
+        .await
+        else {
+            return Ok(());
+        };
+
+        let message = err.to_string().to_lowercase();
+        if MISSING_STATE_ERRORS.iter().any(|fragment| message.contains(fragment)) {
+            eyre::bail!(
+                "the RPC node can't serve the state at block {}, an archive node is required: {}",
+                block_number,
+                err
+            );
+        }
+
+        Err(err)
+    }
+
+    /// Fetches the proof of an account and of the given storage keys at a block, splitting the
+    /// keys over several requests when there are more than `max_keys_per_proof`.
+    async fn fetch_proof(
+        &self,
+        address: Address,
+        keys: &[B256],
+        block_number: u64,
+        retry_budget: &RetryBudget,
+    ) -> eyre::Result<EIP1186AccountProofResponse> {
+        let chunk_size = self.max_keys_per_proof.unwrap_or(usize::MAX).max(1);
+        let mut chunks = keys.chunks(chunk_size);
+
+        let mut proof = self
+            .fetch_proof_chunk(
+                address,
+                chunks.next().unwrap_or_default(),
+                block_number,
+                retry_budget,
+            )
+            .await?;
+        for chunk in chunks {
+            let chunk_proof =
+                self.fetch_proof_chunk(address, chunk, block_number, retry_budget).await?;
+            proof.storage_proof.extend(chunk_proof.storage_proof);
+        }
+
+        Ok(proof)
+    }
+
+    /// Fetches the proof of an account and of a single chunk of storage keys at a block.
+    async fn fetch_proof_chunk(
+        &self,
+        address: Address,
+        keys: &[B256],
+        block_number: u64,
+        retry_budget: &RetryBudget,
+    ) -> eyre::Result<EIP1186AccountProofResponse> {
+        request(retry_budget, || async {
+            Ok(self
+                .provider
+                .get_proof(address, keys.to_vec())
+                .block_id(block_number.into())
+                .await?)
+        })
+        .await
+    }
+
+    /// Returns a new [RetryBudget] for the requests of a block.
+    fn new_retry_budget(&self) -> RetryBudget {
+        RetryBudget::new(self.retry_budget.unwrap_or_default())
+            .with_retryable_errors(self.retryable_errors.clone())
+    }
+}
+
+/// Sends a request, retrying it while it fails with a transient transport error and the
+/// [RetryBudget] allows.
+async fn request<R, F, Fut>(retry_budget: &RetryBudget, send: F) -> eyre::Result<R>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = eyre::Result<R>>,
+{
+    let is_transient_report = |err: &eyre::Report| {
+        err.downcast_ref::<TransportError>().is_some_and(|err| retry_budget.is_transient(err))
+    };
+    retry_budget.retry(is_transient_report, send).await.map_err(|err| match err {
+        RetryError::Failed(err) => err,
+        RetryError::BudgetExhausted(err) => {
+            eyre!("retry budget exhausted, last error: {}", err)
+        }
+    })
 }
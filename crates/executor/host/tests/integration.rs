@@ -1,4 +1,5 @@
 use alloy_provider::ReqwestProvider;
+use revm_primitives::calc_blob_gasprice;
 use rsp_client_executor::{
     io::ClientExecutorInput, ChainVariant, ClientExecutor, EthereumVariant, LineaVariant,
     OptimismVariant, Variant,
@@ -24,6 +25,38 @@ async fn test_e2e_linea() {
     run_e2e::<LineaVariant>(ChainVariant::Linea, "RPC_59144", 5600000).await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_e2e_blob_base_fee_override() {
+    // Intialize the environment variables.
+    dotenv::dotenv().ok();
+
+    // Setup the provider.
+    let rpc_url = Url::parse(std::env::var("RPC_1").unwrap().as_str()).expect("invalid rpc url");
+    let provider = ReqwestProvider::new_http(rpc_url);
+
+    // Execute the host for a post-Cancun block.
+    let client_input = HostExecutor::new(provider)
+        .execute(20600000, ChainVariant::Ethereum)
+        .await
+        .expect("failed to execute host");
+    let header = &client_input.current_block.header;
+    let client_executor = ClientExecutor;
+
+    // Overriding the blob base fee with its actual value doesn't change the block.
+    let blob_base_fee = calc_blob_gasprice(header.excess_blob_gas.expect("missing blob gas"));
+    let state_root = client_executor
+        .execute_with_blob_base_fee::<EthereumVariant>(client_input.clone(), blob_base_fee)
+        .expect("failed to execute the block with its blob base fee");
+    assert_eq!(state_root, header.state_root);
+
+    // A lower blob base fee changes the fees burned by blob transactions, if there are any.
+    let has_blob_txs = client_input.current_block.body.iter().any(|tx| tx.is_eip4844());
+    let state_root = client_executor
+        .execute_with_blob_base_fee::<EthereumVariant>(client_input.clone(), 0)
+        .expect("failed to execute the block with an overridden blob base fee");
+    assert_eq!(state_root != header.state_root, has_blob_txs);
+}
+
 async fn run_e2e<V>(variant: ChainVariant, env_var_key: &str, block_number: u64)
 where
     V: Variant,
@@ -53,7 +86,14 @@ where
     let client_executor = ClientExecutor;
 
     // Execute the client.
-    client_executor.execute::<V>(client_input.clone()).expect("failed to execute client");
+    let header =
+        client_executor.execute::<V>(client_input.clone()).expect("failed to execute client");
+
+    // Compute only the post-state root, which must match the one of the derived header.
+    let state_root = client_executor
+        .compute_post_state_root::<V>(client_input.clone())
+        .expect("failed to compute the post-state root");
+    assert_eq!(state_root, header.state_root);
 
     // Save the client input to a buffer.
     let buffer = bincode::serialize(&client_input).unwrap();
@@ -1,11 +1,70 @@
-use eyre::Result;
-use reth_trie::{AccountProof, HashedPostState, TrieAccount};
-use revm::primitives::{Address, HashMap, B256};
+use std::{collections::BTreeSet, iter::once};
+
+use reth_trie::{HashedPostState, TrieAccount};
+use revm::primitives::{keccak256, Address, Bytes, HashMap, B256, KECCAK_EMPTY, U256};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "host")]
+use reth_trie::AccountProof;
+
 /// Module containing MPT code adapted from `zeth`.
 mod mpt;
-use mpt::{proofs_to_tries, transition_proofs_to_tries, MptNode};
+pub use mpt::Error as MptError;
+use mpt::MptNode;
+#[cfg(feature = "host")]
+use mpt::{proofs_to_tries, transition_proofs_to_tries};
+
+/// Errors that can occur when building an [`EthereumState`] from proofs.
+#[cfg(feature = "host")]
+#[derive(Debug, thiserror::Error)]
+pub enum FromProofError {
+    #[error("invalid proof for account {address}")]
+    InvalidProof {
+        address: Address,
+        #[source]
+        source: MptError,
+    },
+    #[error("invalid proof for storage slot {slot} of account {address}")]
+    InvalidStorageProof {
+        address: Address,
+        slot: B256,
+        #[source]
+        source: MptError,
+    },
+    #[error("missing post-state proof for account {0}")]
+    MissingPostStateProof(Address),
+    #[error("mismatched storage root for account {address}: expected={expected}, got={got}")]
+    MismatchedStorageRoot { address: Address, expected: B256, got: B256 },
+    #[error("mismatched state root: expected={expected}, got={got}")]
+    MismatchedStateRoot { expected: B256, got: B256 },
+    #[error("mismatched account {0}")]
+    MismatchedAccount(Address),
+    #[error("account {0} is not covered by its proof, which may be empty")]
+    UnresolvedAccount(Address),
+    #[error("storage slot {slot} of account {address} is not covered by its proof")]
+    UnresolvedStorage { address: Address, slot: B256 },
+}
+
+/// Errors that can occur when applying updates to an [`EthereumState`] that wasn't witnessed well
+/// enough for them.
+#[derive(Debug, thiserror::Error)]
+pub enum IncompleteStateError {
+    #[error("unresolved node in the state trie at account {hashed_address}")]
+    UnresolvedAccount {
+        hashed_address: B256,
+        #[source]
+        source: MptError,
+    },
+    #[error("unresolved node in the storage trie of account {hashed_address} at slot {slot}")]
+    UnresolvedStorage {
+        hashed_address: B256,
+        slot: B256,
+        #[source]
+        source: MptError,
+    },
+    #[error("missing storage trie for account {0}")]
+    MissingStorageTrie(B256),
+}
 
 /// Ethereum state trie and account storage tries.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -14,43 +73,140 @@ pub struct EthereumState {
     pub storage_tries: HashMap<B256, MptNode>,
 }
 
+/// The difference between the resolved nodes of two tries, identified by their hashes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrieDiff {
+    /// Hashes of the nodes only present in the new trie.
+    pub added: Vec<B256>,
+    /// Hashes of the nodes only present in the old trie.
+    pub removed: Vec<B256>,
+}
+
+impl TrieDiff {
+    /// Computes the difference between two optional tries, where a missing trie has no nodes.
+    fn between(old: Option<&MptNode>, new: Option<&MptNode>) -> Self {
+        let node_hashes = |trie: Option<&MptNode>| {
+            trie.map(|trie| trie.rlp_nodes().iter().map(keccak256).collect::<BTreeSet<_>>())
+                .unwrap_or_default()
+        };
+        let old = node_hashes(old);
+        let new = node_hashes(new);
+
+        Self {
+            added: new.difference(&old).copied().collect(),
+            removed: old.difference(&new).copied().collect(),
+        }
+    }
+
+    /// Returns `true` if both tries contain the same nodes.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The difference between two [`EthereumState`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// The difference between the state tries.
+    pub state_trie: TrieDiff,
+    /// The differences between the storage tries that changed, indexed by hashed address.
+    pub storage_tries: HashMap<B256, TrieDiff>,
+}
+
 impl EthereumState {
     /// Builds Ethereum state tries from relevant proofs before and after a state transition.
+    #[cfg(feature = "host")]
     pub fn from_transition_proofs(
         state_root: B256,
         parent_proofs: &HashMap<Address, AccountProof>,
         proofs: &HashMap<Address, AccountProof>,
-    ) -> Result<Self> {
+    ) -> Result<Self, FromProofError> {
         transition_proofs_to_tries(state_root, parent_proofs, proofs)
-            .map_err(|err| eyre::eyre!("{}", err))
     }
 
     /// Builds Ethereum state tries from relevant proofs from a given state.
-    pub fn from_proofs(state_root: B256, proofs: &HashMap<Address, AccountProof>) -> Result<Self> {
-        proofs_to_tries(state_root, proofs).map_err(|err| eyre::eyre!("{}", err))
+    #[cfg(feature = "host")]
+    pub fn from_proofs(
+        state_root: B256,
+        proofs: &HashMap<Address, AccountProof>,
+    ) -> Result<Self, FromProofError> {
+        proofs_to_tries(state_root, proofs)
+    }
+
+    /// Checks that the account and storage root of every address in `proofs` match this state.
+    /// Unlike [`Self::from_proofs`], the proof nodes themselves are not checked against a state
+    /// root.
+    #[cfg(feature = "host")]
+    pub fn verify_proofs(
+        &self,
+        proofs: &HashMap<Address, AccountProof>,
+    ) -> Result<(), FromProofError> {
+        for (address, proof) in proofs {
+            let hashed_address = keccak256(address);
+
+            let account = self
+                .state_trie
+                .get_rlp::<TrieAccount>(hashed_address.as_slice())
+                .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
+            let expected = proof.info.map(|info| TrieAccount {
+                nonce: info.nonce,
+                balance: info.balance,
+                storage_root: proof.storage_root,
+                code_hash: info.get_bytecode_hash(),
+            });
+            if account != expected {
+                return Err(FromProofError::MismatchedAccount(*address));
+            }
+
+            if let Some(storage_trie) = self.storage_tries.get(&hashed_address) {
+                if storage_trie.hash() != proof.storage_root {
+                    return Err(FromProofError::MismatchedStorageRoot {
+                        address: *address,
+                        expected: proof.storage_root,
+                        got: storage_trie.hash(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    /// Mutates state based on diffs provided in [`HashedPostState`].
-    pub fn update(&mut self, post_state: &HashedPostState) {
+    /// Mutates state based on diffs provided in [`HashedPostState`]. Fails if the tries don't
+    /// contain the nodes needed to apply the diffs, i.e. the state was witnessed insufficiently.
+    pub fn update(&mut self, post_state: &HashedPostState) -> Result<(), IncompleteStateError> {
         for (hashed_address, account) in post_state.accounts.iter() {
-            let hashed_address = hashed_address.as_slice();
+            let unresolved_account = |source| IncompleteStateError::UnresolvedAccount {
+                hashed_address: *hashed_address,
+                source,
+            };
 
             match account {
                 Some(account) => {
                     let state_storage = &post_state.storages.get(hashed_address).unwrap();
                     let storage_root = {
-                        let storage_trie = self.storage_tries.get_mut(hashed_address).unwrap();
+                        let storage_trie = self
+                            .storage_tries
+                            .get_mut(hashed_address)
+                            .ok_or(IncompleteStateError::MissingStorageTrie(*hashed_address))?;
 
                         if state_storage.wiped {
                             storage_trie.clear();
                         }
 
                         for (key, value) in state_storage.storage.iter() {
-                            let key = key.as_slice();
+                            let unresolved_storage =
+                                |source| IncompleteStateError::UnresolvedStorage {
+                                    hashed_address: *hashed_address,
+                                    slot: *key,
+                                    source,
+                                };
                             if value.is_zero() {
-                                storage_trie.delete(key).unwrap();
+                                storage_trie.delete(key.as_slice()).map_err(unresolved_storage)?;
                             } else {
-                                storage_trie.insert_rlp(key, *value).unwrap();
+                                storage_trie
+                                    .insert_rlp(key.as_slice(), *value)
+                                    .map_err(unresolved_storage)?;
                             }
                         }
 
@@ -63,13 +219,77 @@ impl EthereumState {
                         storage_root,
                         code_hash: account.get_bytecode_hash(),
                     };
-                    self.state_trie.insert_rlp(hashed_address, state_account).unwrap();
+                    self.state_trie
+                        .insert_rlp(hashed_address.as_slice(), state_account)
+                        .map_err(unresolved_account)?;
                 }
                 None => {
-                    self.state_trie.delete(hashed_address).unwrap();
+                    self.state_trie
+                        .delete(hashed_address.as_slice())
+                        .map_err(unresolved_account)?;
                 }
             }
         }
+
+        Ok(())
+    }
+
+    /// Clears the storage trie of the account at `address`, e.g. when it self-destructed. The
+    /// account itself is left untouched in the state trie.
+    pub fn clear_storage(&mut self, address: Address) {
+        if let Some(storage_trie) = self.storage_tries.get_mut(&keccak256(address)) {
+            storage_trie.clear();
+        }
+    }
+
+    /// Returns the deduplicated RLP encodings of all resolved nodes of the state trie and the
+    /// storage tries.
+    pub fn trie_nodes(&self) -> Vec<Bytes> {
+        once(&self.state_trie)
+            .chain(self.storage_tries.values())
+            .flat_map(|trie| trie.rlp_nodes())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(Bytes::from)
+            .collect()
+    }
+
+    /// Returns the RLP encodings of all resolved nodes of the state trie and the storage tries,
+    /// indexed by their keccak hash.
+    pub fn trie_node_preimages(&self) -> HashMap<B256, Bytes> {
+        self.trie_nodes().into_iter().map(|node| (keccak256(&node), node)).collect()
+    }
+
+    /// Computes the nodes added and removed when going from `self` to `other`, for both the state
+    /// trie and the storage tries. Storage tries that didn't change are omitted.
+    pub fn diff(&self, other: &EthereumState) -> StateDiff {
+        let storage_tries = self
+            .storage_tries
+            .keys()
+            .chain(other.storage_tries.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|hashed_address| {
+                let diff = TrieDiff::between(
+                    self.storage_tries.get(hashed_address),
+                    other.storage_tries.get(hashed_address),
+                );
+                (!diff.is_empty()).then_some((*hashed_address, diff))
+            })
+            .collect();
+
+        StateDiff {
+            state_trie: TrieDiff::between(Some(&self.state_trie), Some(&other.state_trie)),
+            storage_tries,
+        }
+    }
+
+    /// Returns whether the account at `address` has code, verified against the state trie.
+    /// Accounts that don't exist are not contracts.
+    pub fn is_contract(&self, address: Address) -> Result<bool, MptError> {
+        let account = self.state_trie.get_rlp::<TrieAccount>(keccak256(address).as_slice())?;
+
+        Ok(account.is_some_and(|account| account.code_hash != KECCAK_EMPTY))
     }
 
     /// Computes the state root.
@@ -77,3 +297,186 @@ impl EthereumState {
         self.state_trie.hash()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_diff() {
+        let mut state_trie = MptNode::default();
+        for i in 0..16u8 {
+            state_trie.insert_rlp(&keccak256([i]).0, [i; 32]).unwrap();
+        }
+        let old = EthereumState { state_trie, storage_tries: HashMap::new() };
+
+        assert_eq!(old.diff(&old), StateDiff::default());
+
+        let mut new = old.clone();
+        new.state_trie.insert_rlp(&keccak256([0u8]).0, [0xffu8; 32]).unwrap();
+        new.storage_tries.insert(B256::ZERO, old.state_trie.clone());
+
+        let diff = old.diff(&new);
+        assert!(!diff.state_trie.added.is_empty());
+        assert!(!diff.state_trie.removed.is_empty());
+        assert!(diff.state_trie.added.contains(&new.state_root()));
+        assert!(diff.state_trie.removed.contains(&old.state_root()));
+        assert_eq!(diff.storage_tries.len(), 1);
+        assert!(diff.storage_tries[&B256::ZERO].removed.is_empty());
+
+        let reverse = new.diff(&old);
+        assert_eq!(reverse.state_trie.added, diff.state_trie.removed);
+        assert_eq!(reverse.state_trie.removed, diff.state_trie.added);
+    }
+
+    #[test]
+    pub fn test_trie_node_preimages() {
+        let mut state_trie = MptNode::default();
+        for i in 0..16u8 {
+            state_trie.insert_rlp(&keccak256([i]).0, [i; 32]).unwrap();
+        }
+        let state = EthereumState {
+            state_trie: state_trie.clone(),
+            storage_tries: HashMap::from_iter([(B256::ZERO, state_trie)]),
+        };
+
+        let preimages = state.trie_node_preimages();
+        assert_eq!(preimages.len(), state.trie_nodes().len());
+        assert!(preimages.contains_key(&state.state_root()));
+        assert!(preimages.iter().all(|(hash, node)| keccak256(node) == *hash));
+    }
+
+    #[test]
+    pub fn test_clear_storage() {
+        let address = Address::repeat_byte(1);
+
+        let mut storage_trie = MptNode::default();
+        storage_trie.insert_rlp(&keccak256([1u8]).0, revm::primitives::U256::from(1)).unwrap();
+        let mut state = EthereumState {
+            state_trie: MptNode::default(),
+            storage_tries: HashMap::from_iter([(keccak256(address), storage_trie)]),
+        };
+
+        state.clear_storage(Address::repeat_byte(2));
+        assert_ne!(state.storage_tries[&keccak256(address)].hash(), reth_trie::EMPTY_ROOT_HASH);
+
+        state.clear_storage(address);
+        assert_eq!(state.storage_tries[&keccak256(address)].hash(), reth_trie::EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    pub fn test_update_incomplete_state() {
+        let hashed_address = keccak256(Address::repeat_byte(1));
+        let slot = keccak256([1u8]);
+
+        let mut post_state = HashedPostState::default();
+        post_state.accounts.insert(
+            hashed_address,
+            Some(reth_primitives::Account { nonce: 1, ..Default::default() }),
+        );
+        post_state.storages.insert(
+            hashed_address,
+            reth_trie::HashedStorage::from_iter(false, [(slot, U256::from(1))]),
+        );
+
+        // A fully known state can be updated.
+        let mut state = EthereumState {
+            state_trie: MptNode::default(),
+            storage_tries: HashMap::from_iter([(hashed_address, MptNode::default())]),
+        };
+        state.update(&post_state).unwrap();
+
+        // A storage trie that is only known by its digest can't be updated.
+        let mut incomplete = EthereumState {
+            state_trie: MptNode::default(),
+            storage_tries: HashMap::from_iter([(
+                hashed_address,
+                mpt::MptNodeData::Digest(B256::repeat_byte(1)).into(),
+            )]),
+        };
+        assert!(matches!(
+            incomplete.update(&post_state),
+            Err(IncompleteStateError::UnresolvedStorage { .. })
+        ));
+
+        // Neither can a state trie that is only known by its digest.
+        let mut incomplete = EthereumState {
+            state_trie: mpt::MptNodeData::Digest(B256::repeat_byte(1)).into(),
+            storage_tries: HashMap::from_iter([(hashed_address, MptNode::default())]),
+        };
+        assert!(matches!(
+            incomplete.update(&post_state),
+            Err(IncompleteStateError::UnresolvedAccount { .. })
+        ));
+
+        // Nor a state missing the storage trie of an updated account.
+        let mut incomplete =
+            EthereumState { state_trie: MptNode::default(), storage_tries: HashMap::new() };
+        assert!(matches!(
+            incomplete.update(&post_state),
+            Err(IncompleteStateError::MissingStorageTrie(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_is_contract() {
+        let eoa = Address::repeat_byte(1);
+        let contract = Address::repeat_byte(2);
+
+        let mut state_trie = MptNode::default();
+        let account = TrieAccount { code_hash: KECCAK_EMPTY, ..Default::default() };
+        state_trie.insert_rlp(keccak256(eoa).as_slice(), account).unwrap();
+        let account = TrieAccount { code_hash: keccak256([0x00]), ..Default::default() };
+        state_trie.insert_rlp(keccak256(contract).as_slice(), account).unwrap();
+        let state = EthereumState { state_trie, storage_tries: HashMap::new() };
+
+        assert!(!state.is_contract(eoa).unwrap());
+        assert!(state.is_contract(contract).unwrap());
+        assert!(!state.is_contract(Address::repeat_byte(3)).unwrap());
+    }
+
+    #[test]
+    pub fn test_verify_proofs() {
+        let address = Address::repeat_byte(1);
+        let info = reth_primitives::Account {
+            nonce: 1,
+            balance: revm::primitives::U256::from(2),
+            bytecode_hash: None,
+        };
+
+        let mut state_trie = MptNode::default();
+        let account = TrieAccount {
+            nonce: info.nonce,
+            balance: info.balance,
+            storage_root: reth_trie::EMPTY_ROOT_HASH,
+            code_hash: KECCAK_EMPTY,
+        };
+        state_trie.insert_rlp(keccak256(address).as_slice(), account).unwrap();
+        let storage_tries = HashMap::from_iter([(keccak256(address), MptNode::default())]);
+        let state = EthereumState { state_trie, storage_tries };
+
+        let proof = AccountProof {
+            address,
+            info: Some(info),
+            proof: vec![],
+            storage_root: reth_trie::EMPTY_ROOT_HASH,
+            storage_proofs: vec![],
+        };
+        let mut proofs = HashMap::from_iter([(address, proof.clone())]);
+        state.verify_proofs(&proofs).unwrap();
+
+        // A missing account only matches a proof of absence.
+        let missing = Address::repeat_byte(2);
+        proofs.insert(missing, AccountProof { address: missing, ..proof.clone() });
+        assert!(matches!(
+            state.verify_proofs(&proofs),
+            Err(FromProofError::MismatchedAccount(address)) if address == missing
+        ));
+        proofs.insert(missing, AccountProof { address: missing, info: None, ..proof.clone() });
+        state.verify_proofs(&proofs).unwrap();
+
+        let info = reth_primitives::Account { nonce: 2, ..info };
+        proofs.insert(address, AccountProof { info: Some(info), ..proof });
+        assert!(matches!(state.verify_proofs(&proofs), Err(FromProofError::MismatchedAccount(_))));
+    }
+}
@@ -28,17 +28,20 @@ use core::{
     fmt::{Debug, Write},
     iter, mem,
 };
+#[cfg(feature = "host")]
 use reth_trie::AccountProof;
+#[cfg(feature = "host")]
 use revm::primitives::HashMap;
 
 use rlp::{Decodable, DecoderError, Prototype, Rlp};
 use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
-use anyhow::{Context, Result};
+#[cfg(feature = "host")]
 use reth_primitives::Address;
 
-use super::EthereumState;
+#[cfg(feature = "host")]
+use super::{EthereumState, FromProofError};
 
 pub trait RlpBytes {
     /// Returns the RLP-encoding.
@@ -732,6 +735,31 @@ impl MptNode {
         }
     }
 
+    /// Returns the RLP encodings of all resolved nodes that are referenced by their hash, i.e.
+    /// the nodes a state database stores individually. The root node is always included.
+    ///
+    /// Nodes shorter than 32 bytes are embedded in their parent's encoding and are therefore
+    /// not returned separately.
+    pub fn rlp_nodes(&self) -> Vec<Vec<u8>> {
+        let mut nodes = Vec::new();
+        self.collect_rlp_nodes(&mut nodes, true);
+        nodes
+    }
+
+    fn collect_rlp_nodes(&self, out: &mut Vec<Vec<u8>>, is_root: bool) {
+        match self.as_data() {
+            MptNodeData::Null | MptNodeData::Digest(_) => return,
+            MptNodeData::Branch(children) => {
+                children.iter().flatten().for_each(|child| child.collect_rlp_nodes(out, false))
+            }
+            MptNodeData::Extension(_, child) => child.collect_rlp_nodes(out, false),
+            MptNodeData::Leaf(_, _) => {}
+        }
+        if is_root || matches!(self.reference(), MptNodeReference::Digest(_)) {
+            out.push(alloy_rlp::encode(self));
+        }
+    }
+
     /// Formats the trie as a string list, where each line corresponds to a trie leaf.
     ///
     /// This method is primarily used for debugging purposes, providing a visual
@@ -847,14 +875,16 @@ fn prefix_nibs(prefix: &[u8]) -> Vec<u8> {
     result
 }
 
+#[cfg(feature = "host")]
 /// Parses proof bytes into a vector of MPT nodes.
-pub fn parse_proof(proof: &[impl AsRef<[u8]>]) -> Result<Vec<MptNode>> {
-    Ok(proof.iter().map(MptNode::decode).collect::<Result<Vec<_>, _>>()?)
+pub fn parse_proof(proof: &[impl AsRef<[u8]>]) -> Result<Vec<MptNode>, Error> {
+    proof.iter().map(MptNode::decode).collect()
 }
 
+#[cfg(feature = "host")]
 /// Creates a Merkle Patricia trie from an EIP-1186 proof.
 /// For inclusion proofs the returned trie contains exactly one leaf with the value.
-pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
+pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode, Error> {
     let mut next: Option<MptNode> = None;
     for (i, node) in proof_nodes.iter().enumerate().rev() {
         // there is nothing to replace for the last node
@@ -897,15 +927,17 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
     Ok(next.unwrap_or_default())
 }
 
+#[cfg(feature = "host")]
 /// Verifies that the given proof is a valid proof of exclusion for the given key.
-pub fn is_not_included(key: &[u8], proof_nodes: &[MptNode]) -> Result<bool> {
-    let proof_trie = mpt_from_proof(proof_nodes).unwrap();
+pub fn is_not_included(key: &[u8], proof_nodes: &[MptNode]) -> Result<bool, Error> {
+    let proof_trie = mpt_from_proof(proof_nodes)?;
     // for valid proofs, the get must not fail
-    let value = proof_trie.get(key).unwrap();
+    let value = proof_trie.get(key)?;
 
     Ok(value.is_none())
 }
 
+#[cfg(feature = "host")]
 /// Creates a new MPT trie where all the digests contained in `node_store` are resolved.
 pub fn resolve_nodes(root: &MptNode, node_store: &HashMap<MptNodeReference, MptNode>) -> MptNode {
     let trie = match root.as_data() {
@@ -935,6 +967,7 @@ pub fn resolve_nodes(root: &MptNode, node_store: &HashMap<MptNodeReference, MptN
     trie
 }
 
+#[cfg(feature = "host")]
 /// Returns a list of all possible nodes that can be created by shortening the path of the
 /// given node.
 /// When nodes in an MPT are deleted, leaves or extensions may be extended. To still be
@@ -961,10 +994,11 @@ pub fn shorten_node_path(node: &MptNode) -> Vec<MptNode> {
     res
 }
 
+#[cfg(feature = "host")]
 pub fn proofs_to_tries(
     state_root: B256,
     proofs: &HashMap<Address, AccountProof>,
-) -> Result<EthereumState> {
+) -> Result<EthereumState, FromProofError> {
     // if no addresses are provided, return the trie only consisting of the state root
     if proofs.is_empty() {
         return Ok(EthereumState {
@@ -976,10 +1010,15 @@ pub fn proofs_to_tries(
     let mut storage: HashMap<B256, MptNode> = HashMap::with_capacity(proofs.len());
 
     let mut state_nodes = HashMap::new();
-    let mut state_root_node = MptNode::default();
+    // start from the state root itself, so that empty proofs (e.g. of accounts that never existed,
+    // as returned by some providers) leave the path of the account unresolved instead of empty,
+    // which is reported once the trie is built
+    let mut state_root_node = node_from_digest(state_root);
     for (address, proof) in proofs {
-        let proof_nodes = parse_proof(&proof.proof).unwrap();
-        mpt_from_proof(&proof_nodes).unwrap();
+        let proof_nodes = parse_proof(&proof.proof)
+            .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
+        mpt_from_proof(&proof_nodes)
+            .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
 
         // the first node in the proof is the root
         if let Some(node) = proof_nodes.first() {
@@ -999,10 +1038,15 @@ pub fn proofs_to_tries(
         }
 
         let mut storage_nodes = HashMap::new();
-        let mut storage_root_node = MptNode::default();
+        let mut storage_root_node = node_from_digest(storage_root);
         for storage_proof in &proof.storage_proofs {
-            let proof_nodes = parse_proof(&storage_proof.proof).unwrap();
-            mpt_from_proof(&proof_nodes).unwrap();
+            let invalid_storage_proof = |source| FromProofError::InvalidStorageProof {
+                address: *address,
+                slot: storage_proof.key,
+                source,
+            };
+            let proof_nodes = parse_proof(&storage_proof.proof).map_err(invalid_storage_proof)?;
+            mpt_from_proof(&proof_nodes).map_err(invalid_storage_proof)?;
 
             // the first node in the proof is the root
             if let Some(node) = proof_nodes.first() {
@@ -1016,21 +1060,47 @@ pub fn proofs_to_tries(
 
         // create the storage trie, from all the relevant nodes
         let storage_trie = resolve_nodes(&storage_root_node, &storage_nodes);
-        assert_eq!(storage_trie.hash(), storage_root);
+        if storage_trie.hash() != storage_root {
+            return Err(FromProofError::MismatchedStorageRoot {
+                address: *address,
+                expected: storage_root,
+                got: storage_trie.hash(),
+            });
+        }
+        if let Some(storage_proof) = proof
+            .storage_proofs
+            .iter()
+            .find(|storage_proof| !is_resolved(&storage_trie, &keccak(storage_proof.key)))
+        {
+            return Err(FromProofError::UnresolvedStorage {
+                address: *address,
+                slot: storage_proof.key,
+            });
+        }
 
         storage.insert(B256::from(&keccak(address)), storage_trie);
     }
     let state_trie = resolve_nodes(&state_root_node, &state_nodes);
-    assert_eq!(state_trie.hash(), state_root);
+    if state_trie.hash() != state_root {
+        return Err(FromProofError::MismatchedStateRoot {
+            expected: state_root,
+            got: state_trie.hash(),
+        });
+    }
+    if let Some(address) = proofs.keys().find(|address| !is_resolved(&state_trie, &keccak(address)))
+    {
+        return Err(FromProofError::UnresolvedAccount(*address));
+    }
 
     Ok(EthereumState { state_trie, storage_tries: storage })
 }
 
+#[cfg(feature = "host")]
 pub fn transition_proofs_to_tries(
     state_root: B256,
     parent_proofs: &HashMap<Address, AccountProof>,
     proofs: &HashMap<Address, AccountProof>,
-) -> Result<EthereumState> {
+) -> Result<EthereumState, FromProofError> {
     // if no addresses are provided, return the trie only consisting of the state root
     if parent_proofs.is_empty() {
         return Ok(EthereumState {
@@ -1042,10 +1112,15 @@ pub fn transition_proofs_to_tries(
     let mut storage: HashMap<B256, MptNode> = HashMap::with_capacity(parent_proofs.len());
 
     let mut state_nodes = HashMap::new();
-    let mut state_root_node = MptNode::default();
+    // start from the state root itself, so that empty proofs (e.g. of accounts that never existed,
+    // as returned by some providers) leave the path of the account unresolved instead of empty,
+    // which is reported once the trie is built
+    let mut state_root_node = node_from_digest(state_root);
     for (address, proof) in parent_proofs {
-        let proof_nodes = parse_proof(&proof.proof).unwrap();
-        mpt_from_proof(&proof_nodes).unwrap();
+        let proof_nodes = parse_proof(&proof.proof)
+            .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
+        mpt_from_proof(&proof_nodes)
+            .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
 
         // the first node in the proof is the root
         if let Some(node) = proof_nodes.first() {
@@ -1056,10 +1131,12 @@ pub fn transition_proofs_to_tries(
             state_nodes.insert(node.reference(), node);
         });
 
-        let fini_proofs = proofs.get(address).unwrap();
+        let fini_proofs =
+            proofs.get(address).ok_or(FromProofError::MissingPostStateProof(*address))?;
 
         // assure that addresses can be deleted from the state trie
-        add_orphaned_leafs(address, &fini_proofs.proof, &mut state_nodes)?;
+        add_orphaned_leafs(address, &fini_proofs.proof, &mut state_nodes)
+            .map_err(|source| FromProofError::InvalidProof { address: *address, source })?;
 
         // if no slots are provided, return the trie only consisting of the storage root
         let storage_root = proof.storage_root;
@@ -1070,10 +1147,15 @@ pub fn transition_proofs_to_tries(
         }
 
         let mut storage_nodes = HashMap::new();
-        let mut storage_root_node = MptNode::default();
+        let mut storage_root_node = node_from_digest(storage_root);
         for storage_proof in &proof.storage_proofs {
-            let proof_nodes = parse_proof(&storage_proof.proof).unwrap();
-            mpt_from_proof(&proof_nodes).unwrap();
+            let invalid_storage_proof = |source| FromProofError::InvalidStorageProof {
+                address: *address,
+                slot: storage_proof.key,
+                source,
+            };
+            let proof_nodes = parse_proof(&storage_proof.proof).map_err(invalid_storage_proof)?;
+            mpt_from_proof(&proof_nodes).map_err(invalid_storage_proof)?;
 
             // the first node in the proof is the root
             if let Some(node) = proof_nodes.first() {
@@ -1087,28 +1169,60 @@ pub fn transition_proofs_to_tries(
 
         // assure that slots can be deleted from the storage trie
         for storage_proof in &fini_proofs.storage_proofs {
-            add_orphaned_leafs(storage_proof.key.0, &storage_proof.proof, &mut storage_nodes)?;
+            add_orphaned_leafs(storage_proof.key.0, &storage_proof.proof, &mut storage_nodes)
+                .map_err(|source| FromProofError::InvalidStorageProof {
+                    address: *address,
+                    slot: storage_proof.key,
+                    source,
+                })?;
         }
         // create the storage trie, from all the relevant nodes
         let storage_trie = resolve_nodes(&storage_root_node, &storage_nodes);
-        assert_eq!(storage_trie.hash(), storage_root);
+        if storage_trie.hash() != storage_root {
+            return Err(FromProofError::MismatchedStorageRoot {
+                address: *address,
+                expected: storage_root,
+                got: storage_trie.hash(),
+            });
+        }
+        if let Some(storage_proof) = proof
+            .storage_proofs
+            .iter()
+            .find(|storage_proof| !is_resolved(&storage_trie, &keccak(storage_proof.key)))
+        {
+            return Err(FromProofError::UnresolvedStorage {
+                address: *address,
+                slot: storage_proof.key,
+            });
+        }
 
         storage.insert(B256::from(&keccak(address)), storage_trie);
     }
     let state_trie = resolve_nodes(&state_root_node, &state_nodes);
-    assert_eq!(state_trie.hash(), state_root);
+    if state_trie.hash() != state_root {
+        return Err(FromProofError::MismatchedStateRoot {
+            expected: state_root,
+            got: state_trie.hash(),
+        });
+    }
+    if let Some(address) =
+        parent_proofs.keys().find(|address| !is_resolved(&state_trie, &keccak(address)))
+    {
+        return Err(FromProofError::UnresolvedAccount(*address));
+    }
 
     Ok(EthereumState { state_trie, storage_tries: storage })
 }
 
+#[cfg(feature = "host")]
 /// Adds all the leaf nodes of non-inclusion proofs to the nodes.
 fn add_orphaned_leafs(
     key: impl AsRef<[u8]>,
     proof: &[impl AsRef<[u8]>],
     nodes_by_reference: &mut HashMap<MptNodeReference, MptNode>,
-) -> Result<()> {
+) -> Result<(), Error> {
     if !proof.is_empty() {
-        let proof_nodes = parse_proof(proof).context("invalid proof encoding")?;
+        let proof_nodes = parse_proof(proof)?;
         if is_not_included(&keccak(key), &proof_nodes)? {
             // add the leaf node to the nodes
             let leaf = proof_nodes.last().unwrap();
@@ -1121,6 +1235,14 @@ fn add_orphaned_leafs(
     Ok(())
 }
 
+#[cfg(feature = "host")]
+/// Returns whether the value of `key` can be read from the trie, i.e. whether the proofs the trie
+/// was built from cover its path.
+fn is_resolved(trie: &MptNode, key: &[u8]) -> bool {
+    !matches!(trie.get(key), Err(Error::NodeNotResolved(_)))
+}
+
+#[cfg(feature = "host")]
 /// Creates a new MPT node from a digest.
 fn node_from_digest(digest: B256) -> MptNode {
     match digest {
@@ -1135,6 +1257,76 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    pub fn test_proofs_to_tries_empty_proof() {
+        // some providers return an empty proof for accounts that never existed
+        let empty_proof = |address| AccountProof {
+            address,
+            info: None,
+            proof: vec![],
+            storage_root: EMPTY_ROOT,
+            storage_proofs: vec![],
+        };
+        let first_nibble = |address: &Address| keccak(address)[0] >> 4;
+
+        // a trie with two accounts under a branch, and two accounts that never existed: one whose
+        // path ends at an empty branch slot, and one whose path leads to the other leaf
+        let existing = Address::repeat_byte(1);
+        let candidates = (2..=u8::MAX).map(Address::repeat_byte).collect::<Vec<_>>();
+        let missing = *candidates
+            .iter()
+            .find(|address| first_nibble(address) != first_nibble(&existing))
+            .unwrap();
+        let sibling = *candidates
+            .iter()
+            .find(|address| **address != missing && first_nibble(address) == first_nibble(&missing))
+            .unwrap();
+        let unrelated = *candidates
+            .iter()
+            .find(|address| {
+                ![first_nibble(&existing), first_nibble(&missing)].contains(&first_nibble(address))
+            })
+            .unwrap();
+
+        let mut trie = MptNode::default();
+        trie.insert_rlp(&keccak(existing), [1u8; 32]).unwrap();
+        trie.insert_rlp(&keccak(sibling), [2u8; 32]).unwrap();
+        let MptNodeData::Branch(children) = trie.as_data() else { panic!("expected a branch") };
+        let existing_leaf = children[first_nibble(&existing) as usize].as_ref().unwrap();
+        let existing_proof = AccountProof {
+            proof: vec![trie.to_rlp().into(), existing_leaf.to_rlp().into()],
+            ..empty_proof(existing)
+        };
+
+        // the empty proof of an account outside of the proven paths can still be read
+        let proofs = HashMap::from_iter([
+            (existing, existing_proof.clone()),
+            (unrelated, empty_proof(unrelated)),
+        ]);
+        let state = proofs_to_tries(trie.hash(), &proofs).unwrap();
+        assert_eq!(state.state_trie.get(&keccak(unrelated)).unwrap(), None);
+        assert!(state.state_trie.get(&keccak(existing)).unwrap().is_some());
+        let state = transition_proofs_to_tries(trie.hash(), &proofs, &proofs).unwrap();
+        assert_eq!(state.state_trie.get(&keccak(unrelated)).unwrap(), None);
+
+        // the empty proof of an account whose path isn't proven is rejected
+        let proofs =
+            HashMap::from_iter([(existing, existing_proof), (missing, empty_proof(missing))]);
+        assert!(matches!(
+            proofs_to_tries(trie.hash(), &proofs),
+            Err(FromProofError::UnresolvedAccount(address)) if address == missing
+        ));
+        assert!(matches!(
+            transition_proofs_to_tries(trie.hash(), &proofs, &proofs),
+            Err(FromProofError::UnresolvedAccount(address)) if address == missing
+        ));
+
+        // empty proofs are fine against an empty state
+        let proofs = HashMap::from_iter([(missing, empty_proof(missing))]);
+        let state = proofs_to_tries(EMPTY_ROOT, &proofs).unwrap();
+        assert_eq!(state.state_trie.get(&keccak(missing)).unwrap(), None);
+    }
+
     #[test]
     pub fn test_trie_pointer_no_keccak() {
         let cases = [("do", "verb"), ("dog", "puppy"), ("doge", "coin"), ("horse", "stallion")];
@@ -88,6 +88,9 @@ pub fn linea_mainnet() -> ChainSpec {
         chain: Chain::linea(),
         // We don't need the genesis state. Using default to save cycles.
         genesis: Default::default(),
+        genesis_hash: Some(b256!(
+            "b6762a65689107b2326364aefc18f94cda413209fab35c00d4af51eaa20ffbc6"
+        )),
         paris_block_and_final_difficulty: Some((0, U256::ZERO)),
         // For some reasons a state root mismatch error arises if we don't force activate everything
         // before and including Shanghai.
@@ -114,3 +117,24 @@ pub fn linea_mainnet() -> ChainSpec {
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genesis_hash() {
+        assert_eq!(
+            mainnet().genesis_hash(),
+            b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3")
+        );
+        assert_eq!(
+            op_mainnet().genesis_hash(),
+            b256!("7ca38a1916c42007829c55e69d3e9a73265554b586a499015373241b8a3fa48b")
+        );
+        assert_eq!(
+            linea_mainnet().genesis_hash(),
+            b256!("b6762a65689107b2326364aefc18f94cda413209fab35c00d4af51eaa20ffbc6")
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet},
+    future::Future,
     marker::PhantomData,
+    pin::pin,
 };
 
 use alloy_provider::{network::AnyNetwork, Provider};
 use alloy_rpc_types::BlockId;
-use alloy_transport::Transport;
+use alloy_transport::{Transport, TransportError};
+use futures::future::{select, Either};
 use reth_primitives::{
     revm_primitives::{AccountInfo, Bytecode},
     Address, B256, U256,
@@ -14,6 +17,10 @@ use reth_primitives::{
 use reth_revm::DatabaseRef;
 use reth_storage_errors::{db::DatabaseError, provider::ProviderError};
 use revm_primitives::HashMap;
+use tokio_util::sync::CancellationToken;
+
+mod retry;
+pub use retry::{RetryBudget, RetryError};
 
 /// A database that fetches data from a [Provider] over a [Transport].
 #[derive(Debug, Clone)]
@@ -26,8 +33,18 @@ pub struct RpcDb<T, P> {
     pub accounts: RefCell<HashMap<Address, AccountInfo>>,
     /// The cached storage values.
     pub storage: RefCell<HashMap<Address, HashMap<U256, U256>>>,
+    /// The storage values fetched ahead of execution. These are only moved to `storage` once
+    /// they're actually accessed, so that unused slots don't end up in the state requests.
+    pub prefetched_storage: RefCell<HashMap<Address, HashMap<U256, U256>>>,
     /// The oldest block whose header/hash has been requested.
     pub oldest_ancestor: RefCell<u64>,
+    /// The maximum number of storage keys requested in a single `eth_getProof` call.
+    pub max_keys_per_proof: Option<usize>,
+    /// The token used to abort pending fetches.
+    pub cancellation_token: CancellationToken,
+    /// The budget of retries of failed requests, which may be shared with other fetches of the
+    /// block. By default, failed requests are not retried by the database.
+    pub retry_budget: RetryBudget,
     /// A phantom type to make the struct generic over the transport.
     pub _phantom: PhantomData<T>,
 }
@@ -41,6 +58,10 @@ pub enum RpcDbError {
     BlockNotFound,
     #[error("failed to find trie node preimage")]
     PreimageNotFound,
+    #[error("cancelled")]
+    Cancelled,
+    #[error("retry budget exhausted, last error: {0}")]
+    RetryBudgetExhausted(String),
 }
 
 impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> RpcDb<T, P> {
@@ -51,30 +72,93 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> RpcDb<T, P> {
             block: block.into(),
             accounts: RefCell::new(HashMap::new()),
             storage: RefCell::new(HashMap::new()),
+            prefetched_storage: RefCell::new(HashMap::new()),
             oldest_ancestor: RefCell::new(block),
+            max_keys_per_proof: None,
+            cancellation_token: CancellationToken::new(),
+            retry_budget: RetryBudget::default(),
             _phantom: PhantomData,
         }
     }
 
+    /// Sets the [CancellationToken] that aborts all subsequent fetches once cancelled.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Always fetches the `window` most recent ancestor headers of the executed block, whether or
+    /// not `BLOCKHASH` reaches that far, so that inputs have a deterministic set of ancestors. The
+    /// parent header is always fetched.
+    pub fn with_ancestor_window(mut self, window: u64) -> Self {
+        let parent = self.oldest_ancestor.into_inner();
+        self.oldest_ancestor = RefCell::new((parent + 1).saturating_sub(window).min(parent));
+        self
+    }
+
+    /// Splits the storage keys prefetched for an account over several `eth_getProof` calls when
+    /// there are more than `max_keys_per_proof`, for providers that reject large requests.
+    pub fn with_max_keys_per_proof(mut self, max_keys_per_proof: usize) -> Self {
+        self.max_keys_per_proof = Some(max_keys_per_proof);
+        self
+    }
+
+    /// Retries requests that fail with a transient error as long as the [RetryBudget] allows.
+    pub fn with_retry_budget(mut self, retry_budget: RetryBudget) -> Self {
+        self.retry_budget = retry_budget;
+        self
+    }
+
+    /// Returns an error if the fetches have been cancelled.
+    fn ensure_not_cancelled(&self) -> Result<(), RpcDbError> {
+        if self.cancellation_token.is_cancelled() {
+            return Err(RpcDbError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Sends a request, retrying it on transient failures as long as the retry budget allows.
+    /// Pending requests are dropped as soon as the fetches are cancelled, as the database is
+    /// queried from blocking calls during execution where nothing else can abort them.
+    async fn request<R, F, Fut>(&self, send: F) -> Result<R, RpcDbError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, TransportError>>,
+    {
+        self.ensure_not_cancelled()?;
+        let is_transient = |err: &TransportError| self.retry_budget.is_transient(err);
+        let response = pin!(self.retry_budget.retry(is_transient, send));
+        let cancelled = pin!(self.cancellation_token.cancelled());
+        match select(response, cancelled).await {
+            Either::Left((Ok(response), _)) => Ok(response),
+            Either::Left((Err(RetryError::Failed(err)), _)) => {
+                Err(RpcDbError::RpcError(err.to_string()))
+            }
+            Either::Left((Err(RetryError::BudgetExhausted(err)), _)) => {
+                Err(RpcDbError::RetryBudgetExhausted(err.to_string()))
+            }
+            Either::Right(_) => Err(RpcDbError::Cancelled),
+        }
+    }
+
     /// Fetch the [AccountInfo] for an [Address].
     pub async fn fetch_account_info(&self, address: Address) -> Result<AccountInfo, RpcDbError> {
         tracing::info!("fetching account info for address: {}", address);
+        self.ensure_not_cancelled()?;
 
         // Fetch the proof for the account.
         let proof = self
-            .provider
-            .get_proof(address, vec![])
-            .block_id(self.block)
-            .await
-            .map_err(|e| RpcDbError::RpcError(e.to_string()))?;
+            .request(|| async move {
+                self.provider.get_proof(address, vec![]).block_id(self.block).await
+            })
+            .await?;
 
         // Fetch the code of the account.
         let code = self
-            .provider
-            .get_code_at(address)
-            .block_id(self.block)
-            .await
-            .map_err(|e| RpcDbError::RpcError(e.to_string()))?;
+            .request(
+                || async move { self.provider.get_code_at(address).block_id(self.block).await },
+            )
+            .await?;
 
         // Construct the account info & write it to the log.
         let bytecode = Bytecode::new_raw(code);
@@ -91,6 +175,36 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> RpcDb<T, P> {
         Ok(account_info)
     }
 
+    /// Prefetch the storage values of a known access list (e.g. from a previous run), using a
+    /// single proof request per account instead of one request per slot during execution.
+    pub async fn prefetch_storage(
+        &self,
+        access_list: &HashMap<Address, Vec<U256>>,
+    ) -> Result<(), RpcDbError> {
+        for (&address, slots) in access_list.iter().filter(|(_, slots)| !slots.is_empty()) {
+            tracing::info!("prefetching {} storage values at address: {}", slots.len(), address);
+            self.ensure_not_cancelled()?;
+
+            let keys = slots.iter().map(|slot| B256::from(*slot)).collect::<Vec<_>>();
+            let chunk_size = self.max_keys_per_proof.unwrap_or(usize::MAX).max(1);
+            for chunk in keys.chunks(chunk_size) {
+                let proof = self
+                    .request(|| async move {
+                        self.provider.get_proof(address, chunk.to_vec()).block_id(self.block).await
+                    })
+                    .await?;
+
+                let mut prefetched_storage = self.prefetched_storage.borrow_mut();
+                let entry = prefetched_storage.entry(address).or_default();
+                for storage_proof in proof.storage_proof {
+                    entry.insert(U256::from_be_bytes(storage_proof.key.0 .0), storage_proof.value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Fetch the storage value at an [Address] and [U256] index.
     pub async fn fetch_storage_at(
         &self,
@@ -98,14 +212,24 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> RpcDb<T, P> {
         index: U256,
     ) -> Result<U256, RpcDbError> {
         tracing::info!("fetching storage value at address: {}, index: {}", address, index);
+        self.ensure_not_cancelled()?;
 
-        // Fetch the storage value.
-        let value = self
-            .provider
-            .get_storage_at(address, index)
-            .block_id(self.block)
-            .await
-            .map_err(|e| RpcDbError::RpcError(e.to_string()))?;
+        // Use the prefetched storage value if available, otherwise fetch it.
+        let prefetched_value = self
+            .prefetched_storage
+            .borrow()
+            .get(&address)
+            .and_then(|storage| storage.get(&index))
+            .copied();
+        let value = match prefetched_value {
+            Some(value) => value,
+            None => {
+                self.request(|| async move {
+                    self.provider.get_storage_at(address, index).block_id(self.block).await
+                })
+                .await?
+            }
+        };
 
         // Record the storage value to the state.
         let mut storage_values = self.storage.borrow_mut();
@@ -118,13 +242,14 @@ impl<T: Transport + Clone, P: Provider<T, AnyNetwork> + Clone> RpcDb<T, P> {
     /// Fetch the block hash for a block number.
     pub async fn fetch_block_hash(&self, number: u64) -> Result<B256, RpcDbError> {
         tracing::info!("fetching block hash for block number: {}", number);
+        self.ensure_not_cancelled()?;
 
         // Fetch the block.
         let block = self
-            .provider
-            .get_block_by_number(number.into(), false)
-            .await
-            .map_err(|e| RpcDbError::RpcError(e.to_string()))?;
+            .request(
+                || async move { self.provider.get_block_by_number(number.into(), false).await },
+            )
+            .await?;
 
         // Record the block hash to the state.
         let block = block.ok_or(RpcDbError::BlockNotFound)?;
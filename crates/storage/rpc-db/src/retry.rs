@@ -0,0 +1,206 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy_transport::{RpcError, TransportError};
+
+/// The delay before the first retry of a failed request, doubled on every subsequent retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// The maximum delay between two retries of a failed request.
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// A number of retries of failed requests shared by all the requests made for a block, which
+/// bounds how long fetching the data of a block can take with a degraded provider.
+///
+/// Clones share the same budget. A budget of zero retries never retries.
+#[derive(Debug, Clone, Default)]
+pub struct RetryBudget {
+    /// The total number of retries allowed.
+    retries: u32,
+    /// The number of retries left.
+    remaining: Arc<AtomicU32>,
+    /// Fragments of the error messages returned by the node that are retried, in lowercase.
+    retryable_errors: Arc<Vec<String>>,
+}
+
+/// Errors returned by requests sent through a [RetryBudget].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum RetryError<E> {
+    #[error("{0}")]
+    Failed(E),
+    #[error("retry budget exhausted, last error: {0}")]
+    BudgetExhausted(E),
+}
+
+impl RetryBudget {
+    /// Create a new [`RetryBudget`] allowing `retries` retries in total.
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries,
+            remaining: Arc::new(AtomicU32::new(retries)),
+            retryable_errors: Arc::new(vec![]),
+        }
+    }
+
+    /// Also retries the errors returned by the node whose message contains one of the given
+    /// fragments, case-insensitively, e.g. "header not found" for providers that briefly fail to
+    /// serve the chain tip.
+    pub fn with_retryable_errors(mut self, retryable_errors: Vec<String>) -> Self {
+        self.retryable_errors =
+            Arc::new(retryable_errors.iter().map(|fragment| fragment.to_lowercase()).collect());
+        self
+    }
+
+    /// Returns the number of retries left.
+    pub fn remaining(&self) -> u32 {
+        self.remaining.load(Ordering::SeqCst)
+    }
+
+    /// Sends a request, retrying it with an exponential backoff as long as it fails with an error
+    /// for which `is_transient` holds and the budget isn't exhausted.
+    pub async fn retry<R, E, F, Fut>(
+        &self,
+        is_transient: impl Fn(&E) -> bool,
+        mut send: F,
+    ) -> Result<R, RetryError<E>>
+    where
+        E: Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, E>>,
+    {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let err = match send().await {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+
+            if self.retries == 0 || !is_transient(&err) {
+                return Err(RetryError::Failed(err));
+            }
+            if self
+                .remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                    remaining.checked_sub(1)
+                })
+                .is_err()
+            {
+                return Err(RetryError::BudgetExhausted(err));
+            }
+
+            tracing::warn!(
+                "retrying failed request in {:?} ({} retries left): {}",
+                backoff,
+                self.remaining(),
+                err
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Returns whether a failed request is worth retrying. Transport failures (e.g. dropped
+    /// connections or HTTP errors) are, as well as the errors returned by the node that are
+    /// configured as retryable. Other errors of the node and malformed responses are deterministic
+    /// and would fail again.
+    pub fn is_transient(&self, err: &TransportError) -> bool {
+        match err {
+            RpcError::Transport(_) => true,
+            RpcError::ErrorResp(payload) => {
+                let message = payload.message.to_lowercase();
+                self.retryable_errors.iter().any(|fragment| message.contains(fragment.as_str()))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Sends a request through the budget that fails `failures` times before succeeding, and
+    /// returns the result along with the number of attempts.
+    async fn send(
+        retry_budget: &RetryBudget,
+        failures: u32,
+        transient: bool,
+    ) -> (Result<(), RetryError<String>>, u32) {
+        let attempts = Cell::new(0);
+        let result = retry_budget
+            .retry(
+                |_: &String| transient,
+                || {
+                    attempts.set(attempts.get() + 1);
+                    let result = if attempts.get() > failures {
+                        Ok(())
+                    } else {
+                        Err("request failed".to_string())
+                    };
+                    async move { result }
+                },
+            )
+            .await;
+        (result, attempts.get())
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        tokio::time::pause();
+        let start = tokio::time::Instant::now();
+
+        let retry_budget = RetryBudget::new(3);
+        let (result, attempts) = send(&retry_budget, 2, true).await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+        assert_eq!(retry_budget.remaining(), 1);
+        assert_eq!(start.elapsed(), INITIAL_BACKOFF * 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_shared_across_clones() {
+        tokio::time::pause();
+
+        let retry_budget = RetryBudget::new(3);
+        let (result, attempts) = send(&retry_budget, u32::MAX, true).await;
+        assert!(matches!(result, Err(RetryError::BudgetExhausted(_))));
+        assert_eq!(attempts, 4);
+
+        // The budget is spent for all the clones, which don't retry anymore.
+        let (result, attempts) = send(&retry_budget.clone(), u32::MAX, true).await;
+        assert!(matches!(result, Err(RetryError::BudgetExhausted(_))));
+        assert_eq!(attempts, 1);
+        assert_eq!(retry_budget.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_zero_budget() {
+        tokio::time::pause();
+
+        // Without a budget, failures are returned as is rather than as an exhausted budget.
+        for retry_budget in [RetryBudget::new(0), RetryBudget::default()] {
+            let (result, attempts) = send(&retry_budget, u32::MAX, true).await;
+            assert!(matches!(result, Err(RetryError::Failed(_))));
+            assert_eq!(attempts, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_non_transient_error() {
+        tokio::time::pause();
+
+        let retry_budget = RetryBudget::new(3);
+        let (result, attempts) = send(&retry_budget, u32::MAX, false).await;
+        assert!(matches!(result, Err(RetryError::Failed(_))));
+        assert_eq!(attempts, 1);
+        assert_eq!(retry_budget.remaining(), 3);
+    }
+}